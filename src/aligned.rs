@@ -0,0 +1,117 @@
+use super::*;
+
+use core::ops::{Deref, DerefMut};
+
+/// A type-level alignment, `Align<N>` implements [`Alignment`] for every power of two `N`
+/// up to 4096
+pub struct Align<const N: usize>;
+
+/// An alignment which can be used with [`Aligned`]
+///
+/// # Safety
+///
+/// This trait is sealed, `Archetype` must be a zero-sized type with an alignment of `N`
+#[diagnostic::on_unimplemented(
+    message = "`{Self}` is not a supported alignment",
+    note = "the alignment must be a power of two, at most 4096"
+)]
+pub unsafe trait Alignment: sealed::SealedAlignment {
+    #[doc(hidden)]
+    type Archetype: Copy + Default + Eq + Ord + core::hash::Hash;
+}
+
+macro_rules! alignments {
+    ($($name:ident = $align:literal,)*) => {$(
+        #[doc(hidden)]
+        #[derive(Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+        #[repr(align($align))]
+        pub struct $name;
+
+        impl sealed::SealedAlignment for Align<$align> {}
+
+        unsafe impl Alignment for Align<$align> {
+            type Archetype = $name;
+        }
+    )*};
+}
+
+alignments! {
+    A1 = 1,
+    A2 = 2,
+    A4 = 4,
+    A8 = 8,
+    A16 = 16,
+    A32 = 32,
+    A64 = 64,
+    A128 = 128,
+    A256 = 256,
+    A512 = 512,
+    A1024 = 1024,
+    A2048 = 2048,
+    A4096 = 4096,
+}
+
+/// A `T` which is aligned to at least `ALIGN`
+///
+/// Putting a value behind a pointer to `Aligned` leaves more low bits free for the tag, see
+/// [`AlignedBox`] and [`AlignedArc`]. It derefs to the value, and all of the comparison
+/// traits forward to the value.
+#[derive(Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[repr(C)]
+pub struct Aligned<T, const ALIGN: usize>
+where
+    Align<ALIGN>: Alignment,
+{
+    value: T,
+    _align: [<Align<ALIGN> as Alignment>::Archetype; 0],
+}
+
+impl<T, const ALIGN: usize> Aligned<T, ALIGN>
+where
+    Align<ALIGN>: Alignment,
+{
+    pub const fn new(value: T) -> Self {
+        Self { value, _align: [] }
+    }
+
+    pub fn into_inner(self) -> T {
+        self.value
+    }
+}
+
+impl<T, const ALIGN: usize> Deref for Aligned<T, ALIGN>
+where
+    Align<ALIGN>: Alignment,
+{
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.value
+    }
+}
+
+impl<T, const ALIGN: usize> DerefMut for Aligned<T, ALIGN>
+where
+    Align<ALIGN>: Alignment,
+{
+    fn deref_mut(&mut self) -> &mut T {
+        &mut self.value
+    }
+}
+
+impl<T: core::fmt::Debug, const ALIGN: usize> core::fmt::Debug for Aligned<T, ALIGN>
+where
+    Align<ALIGN>: Alignment,
+{
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        self.value.fmt(f)
+    }
+}
+
+/// A box whose target is aligned to at least `ALIGN`, so it leaves `log2(ALIGN)` low bits
+/// for the tag even when `T` has a small alignment
+pub type AlignedBox<T, const ALIGN: usize> = Box<Aligned<T, ALIGN>>;
+
+/// An [`Arc`](std::sync::Arc) whose target is aligned to at least `ALIGN`,
+/// see [`AlignedBox`]
+pub type AlignedArc<T, const ALIGN: usize> = std::sync::Arc<Aligned<T, ALIGN>>;
@@ -0,0 +1,359 @@
+use super::*;
+
+use core::sync::atomic::{AtomicPtr, AtomicUsize, Ordering};
+
+fn into_bits<Tags: PtrList>(ptr: CopyPtrUnion<Tags>) -> *mut u8 {
+    let (ptr, tag) = ptr.split();
+    unsafe { RawPtrUnion::<Tags>::from_raw_unchecked(ptr, tag) }
+        .into_tagged()
+        .as_ptr()
+}
+
+/// # Safety
+///
+/// `bits` must have come from [`into_bits`] with the same `Tags`
+unsafe fn from_bits<Tags: PtrList>(bits: *mut u8) -> Option<CopyPtrUnion<Tags>> {
+    let ptr = NonNull::new(bits)?;
+    Some(unsafe { CopyPtrUnion::<Tags>::from_raw(RawPtrUnion::from_tagged(ptr)) })
+}
+
+/// A [`CopyPtrUnion`] which can be shared between threads
+///
+/// The pointer and the tag are stored in the same word, so every operation updates both of
+/// them atomically. [`compare_exchange`](Self::compare_exchange) compares the pointer and
+/// the tag, like [`ptr_eq`](PtrUnion::ptr_eq), not the targets.
+#[repr(transparent)]
+pub struct AtomicCopyPtrUnion<Tags: PtrList> {
+    ptr: AtomicPtr<u8>,
+    _ty: PhantomData<*mut Tags>,
+}
+
+/// An optional [`AtomicCopyPtrUnion`], which stores `None` as a null pointer
+#[repr(transparent)]
+pub struct AtomicOptionCopyPtrUnion<Tags: PtrList> {
+    ptr: AtomicPtr<u8>,
+    _ty: PhantomData<*mut Tags>,
+}
+
+unsafe impl<Tags: PtrList + Send> Send for AtomicCopyPtrUnion<Tags> {}
+// loading from a shared reference hands out copies of the pointers to other threads
+unsafe impl<Tags: PtrList + Send + Sync> Sync for AtomicCopyPtrUnion<Tags> {}
+
+unsafe impl<Tags: PtrList + Send> Send for AtomicOptionCopyPtrUnion<Tags> {}
+unsafe impl<Tags: PtrList + Send + Sync> Sync for AtomicOptionCopyPtrUnion<Tags> {}
+
+impl<Tags: PtrList> From<CopyPtrUnion<Tags>> for AtomicCopyPtrUnion<Tags> {
+    fn from(ptr: CopyPtrUnion<Tags>) -> Self {
+        Self::new(ptr)
+    }
+}
+
+impl<Tags: PtrList> AtomicCopyPtrUnion<Tags> {
+    pub fn new(ptr: CopyPtrUnion<Tags>) -> Self {
+        Self {
+            ptr: AtomicPtr::new(into_bits(ptr)),
+            _ty: PhantomData,
+        }
+    }
+
+    fn unwrap(bits: *mut u8) -> CopyPtrUnion<Tags> {
+        // only non-null pointers from `into_bits` are ever stored
+        match unsafe { from_bits(bits) } {
+            Some(ptr) => ptr,
+            None => unsafe { core::hint::unreachable_unchecked() },
+        }
+    }
+
+    pub fn into_inner(self) -> CopyPtrUnion<Tags> {
+        Self::unwrap(self.ptr.into_inner())
+    }
+
+    pub fn load(&self, order: Ordering) -> CopyPtrUnion<Tags> {
+        Self::unwrap(self.ptr.load(order))
+    }
+
+    pub fn store(&self, ptr: CopyPtrUnion<Tags>, order: Ordering) {
+        self.ptr.store(into_bits(ptr), order)
+    }
+
+    pub fn swap(&self, ptr: CopyPtrUnion<Tags>, order: Ordering) -> CopyPtrUnion<Tags> {
+        Self::unwrap(self.ptr.swap(into_bits(ptr), order))
+    }
+
+    /// Stores `new` if the current value has the same pointer and tag as `current`,
+    /// and returns the previous value either way, see [`AtomicPtr::compare_exchange`]
+    pub fn compare_exchange(
+        &self,
+        current: CopyPtrUnion<Tags>,
+        new: CopyPtrUnion<Tags>,
+        success: Ordering,
+        failure: Ordering,
+    ) -> Result<CopyPtrUnion<Tags>, CopyPtrUnion<Tags>> {
+        self.ptr
+            .compare_exchange(into_bits(current), into_bits(new), success, failure)
+            .map(Self::unwrap)
+            .map_err(Self::unwrap)
+    }
+
+    /// Like [`compare_exchange`](Self::compare_exchange), but may fail spuriously,
+    /// see [`AtomicPtr::compare_exchange_weak`]
+    pub fn compare_exchange_weak(
+        &self,
+        current: CopyPtrUnion<Tags>,
+        new: CopyPtrUnion<Tags>,
+        success: Ordering,
+        failure: Ordering,
+    ) -> Result<CopyPtrUnion<Tags>, CopyPtrUnion<Tags>> {
+        self.ptr
+            .compare_exchange_weak(into_bits(current), into_bits(new), success, failure)
+            .map(Self::unwrap)
+            .map_err(Self::unwrap)
+    }
+
+    /// Replaces the value with the result of `f` until it is stored without a concurrent
+    /// update, or until `f` returns `None`, see [`AtomicPtr::fetch_update`]
+    pub fn fetch_update(
+        &self,
+        set_order: Ordering,
+        fetch_order: Ordering,
+        mut f: impl FnMut(CopyPtrUnion<Tags>) -> Option<CopyPtrUnion<Tags>>,
+    ) -> Result<CopyPtrUnion<Tags>, CopyPtrUnion<Tags>> {
+        self.ptr
+            .fetch_update(set_order, fetch_order, |bits| {
+                f(Self::unwrap(bits)).map(into_bits)
+            })
+            .map(Self::unwrap)
+            .map_err(Self::unwrap)
+    }
+}
+
+impl<Tags: PtrList> Default for AtomicOptionCopyPtrUnion<Tags> {
+    fn default() -> Self {
+        Self::new(None)
+    }
+}
+
+impl<Tags: PtrList> From<Option<CopyPtrUnion<Tags>>> for AtomicOptionCopyPtrUnion<Tags> {
+    fn from(ptr: Option<CopyPtrUnion<Tags>>) -> Self {
+        Self::new(ptr)
+    }
+}
+
+impl<Tags: PtrList> AtomicOptionCopyPtrUnion<Tags> {
+    fn into_bits(ptr: Option<CopyPtrUnion<Tags>>) -> *mut u8 {
+        ptr.map_or(core::ptr::null_mut(), into_bits)
+    }
+
+    fn from_bits(bits: *mut u8) -> Option<CopyPtrUnion<Tags>> {
+        unsafe { from_bits(bits) }
+    }
+
+    pub fn new(ptr: Option<CopyPtrUnion<Tags>>) -> Self {
+        Self {
+            ptr: AtomicPtr::new(Self::into_bits(ptr)),
+            _ty: PhantomData,
+        }
+    }
+
+    pub fn into_inner(self) -> Option<CopyPtrUnion<Tags>> {
+        Self::from_bits(self.ptr.into_inner())
+    }
+
+    pub fn load(&self, order: Ordering) -> Option<CopyPtrUnion<Tags>> {
+        Self::from_bits(self.ptr.load(order))
+    }
+
+    pub fn store(&self, ptr: Option<CopyPtrUnion<Tags>>, order: Ordering) {
+        self.ptr.store(Self::into_bits(ptr), order)
+    }
+
+    pub fn swap(
+        &self,
+        ptr: Option<CopyPtrUnion<Tags>>,
+        order: Ordering,
+    ) -> Option<CopyPtrUnion<Tags>> {
+        Self::from_bits(self.ptr.swap(Self::into_bits(ptr), order))
+    }
+
+    /// Replaces the value with `None`, returning the previous value
+    pub fn take(&self, order: Ordering) -> Option<CopyPtrUnion<Tags>> {
+        self.swap(None, order)
+    }
+
+    /// See [`AtomicCopyPtrUnion::compare_exchange`]
+    pub fn compare_exchange(
+        &self,
+        current: Option<CopyPtrUnion<Tags>>,
+        new: Option<CopyPtrUnion<Tags>>,
+        success: Ordering,
+        failure: Ordering,
+    ) -> Result<Option<CopyPtrUnion<Tags>>, Option<CopyPtrUnion<Tags>>> {
+        self.ptr
+            .compare_exchange(
+                Self::into_bits(current),
+                Self::into_bits(new),
+                success,
+                failure,
+            )
+            .map(Self::from_bits)
+            .map_err(Self::from_bits)
+    }
+
+    /// See [`AtomicCopyPtrUnion::compare_exchange_weak`]
+    pub fn compare_exchange_weak(
+        &self,
+        current: Option<CopyPtrUnion<Tags>>,
+        new: Option<CopyPtrUnion<Tags>>,
+        success: Ordering,
+        failure: Ordering,
+    ) -> Result<Option<CopyPtrUnion<Tags>>, Option<CopyPtrUnion<Tags>>> {
+        self.ptr
+            .compare_exchange_weak(
+                Self::into_bits(current),
+                Self::into_bits(new),
+                success,
+                failure,
+            )
+            .map(Self::from_bits)
+            .map_err(Self::from_bits)
+    }
+
+    /// See [`AtomicCopyPtrUnion::fetch_update`]
+    pub fn fetch_update(
+        &self,
+        set_order: Ordering,
+        fetch_order: Ordering,
+        mut f: impl FnMut(Option<CopyPtrUnion<Tags>>) -> Option<Option<CopyPtrUnion<Tags>>>,
+    ) -> Result<Option<CopyPtrUnion<Tags>>, Option<CopyPtrUnion<Tags>>> {
+        self.ptr
+            .fetch_update(set_order, fetch_order, |bits| {
+                f(Self::from_bits(bits)).map(Self::into_bits)
+            })
+            .map(Self::from_bits)
+            .map_err(Self::from_bits)
+    }
+}
+
+/// A [`SharedPtrUnion`] which can be loaded and replaced from many threads, like an
+/// `ArcSwap` for a union of `Arc`s
+///
+/// [`load`](Self::load) clones the active pointer, so readers get their own
+/// `SharedPtrUnion` which stays valid after the value is replaced. Writers swap the pointer
+/// and then wait until every reader which may have seen the previous value has finished
+/// cloning it, before the previous value is released with `PtrList::drop_at`. Readers only
+/// hold up writers for the duration of a clone, but a constant stream of readers can delay
+/// a writer indefinitely.
+pub struct AtomicSharedPtrUnion<Tags: PtrList> {
+    ptr: AtomicPtr<u8>,
+    readers: AtomicUsize,
+    _ty: PhantomData<SharedPtrUnion<Tags>>,
+}
+
+unsafe impl<Tags: PtrList + Send> Send for AtomicSharedPtrUnion<Tags> {}
+// loading from a shared reference hands out clones of the pointers to other threads
+unsafe impl<Tags: PtrList + Send + Sync> Sync for AtomicSharedPtrUnion<Tags> {}
+
+/// Decrements the reader count of an [`AtomicSharedPtrUnion`] when dropped
+struct ReaderGuard<'a>(&'a AtomicUsize);
+
+impl Drop for ReaderGuard<'_> {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::Release);
+    }
+}
+
+impl<Tags: PtrList> Drop for AtomicSharedPtrUnion<Tags> {
+    fn drop(&mut self) {
+        drop(Self::from_bits(*self.ptr.get_mut()))
+    }
+}
+
+impl<Tags: PtrList> From<SharedPtrUnion<Tags>> for AtomicSharedPtrUnion<Tags> {
+    fn from(ptr: SharedPtrUnion<Tags>) -> Self {
+        Self::new(ptr)
+    }
+}
+
+impl<Tags: PtrList> AtomicSharedPtrUnion<Tags> {
+    fn into_bits(ptr: SharedPtrUnion<Tags>) -> *mut u8 {
+        ptr.into_raw().into_tagged().as_ptr()
+    }
+
+    /// Takes ownership of the pointer stored in `bits`
+    fn from_bits(bits: *mut u8) -> SharedPtrUnion<Tags> {
+        // only non-null pointers from `into_bits` are ever stored
+        let ptr = unsafe { NonNull::new_unchecked(bits) };
+        unsafe { SharedPtrUnion::<Tags>::from_raw(RawPtrUnion::from_tagged(ptr)) }
+    }
+
+    /// Waits until no reader can still be cloning a value that was replaced before this call
+    fn wait_for_readers(&self) {
+        while self.readers.load(Ordering::SeqCst) != 0 {
+            core::hint::spin_loop()
+        }
+    }
+
+    pub fn new(ptr: SharedPtrUnion<Tags>) -> Self {
+        Self {
+            ptr: AtomicPtr::new(Self::into_bits(ptr)),
+            readers: AtomicUsize::new(0),
+            _ty: PhantomData,
+        }
+    }
+
+    pub fn into_inner(self) -> SharedPtrUnion<Tags> {
+        let this = core::mem::ManuallyDrop::new(self);
+        Self::from_bits(this.ptr.load(Ordering::Relaxed))
+    }
+
+    /// Clones the current value
+    pub fn load(&self) -> SharedPtrUnion<Tags>
+    where
+        Tags: MapClone,
+    {
+        // the writers can't release the value until we are done cloning it
+        self.readers.fetch_add(1, Ordering::SeqCst);
+        // unregisters this reader even if the clone panics
+        let _reader = ReaderGuard(&self.readers);
+        let bits = self.ptr.load(Ordering::SeqCst);
+        let ptr = core::mem::ManuallyDrop::new(Self::from_bits(bits));
+        SharedPtrUnion::clone(&ptr)
+    }
+
+    /// Replaces the current value and drops the previous value
+    pub fn store(&self, ptr: SharedPtrUnion<Tags>) {
+        drop(self.swap(ptr))
+    }
+
+    /// Replaces the current value and returns the previous value
+    pub fn swap(&self, ptr: SharedPtrUnion<Tags>) -> SharedPtrUnion<Tags> {
+        let old = self.ptr.swap(Self::into_bits(ptr), Ordering::SeqCst);
+        self.wait_for_readers();
+        Self::from_bits(old)
+    }
+
+    /// Stores `new` if the current value has the same pointer and tag as `current`, and
+    /// returns the previous value, otherwise gives `new` back
+    pub fn compare_exchange(
+        &self,
+        current: &SharedPtrUnion<Tags>,
+        new: SharedPtrUnion<Tags>,
+    ) -> Result<SharedPtrUnion<Tags>, SharedPtrUnion<Tags>> {
+        let current = current.split();
+        let current = unsafe { RawPtrUnion::<Tags>::from_raw_unchecked(current.0, current.1) };
+        let new = Self::into_bits(new);
+
+        match self.ptr.compare_exchange(
+            current.into_tagged().as_ptr(),
+            new,
+            Ordering::SeqCst,
+            Ordering::SeqCst,
+        ) {
+            Ok(old) => {
+                self.wait_for_readers();
+                Ok(Self::from_bits(old))
+            }
+            Err(_) => Err(Self::from_bits(new)),
+        }
+    }
+}
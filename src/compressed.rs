@@ -0,0 +1,179 @@
+use super::*;
+
+/// A region of memory that [`CompressedPtrUnion`]s point into
+///
+/// # Safety
+///
+/// `base` must return the same pointer for as long as any pointers compressed against this
+/// arena are in use, and it must have provenance over every pointer that is compressed
+pub unsafe trait Arena {
+    /// The address that the offsets of compressed unions are relative to
+    fn base(&self) -> NonNull<u8>;
+
+    /// Compresses a union of references into this arena into a 32-bit handle
+    ///
+    /// # Panics
+    ///
+    /// if the pointer is before the base of this arena or more than `u32::MAX` bytes after it,
+    /// or if the offset isn't aligned to at least `Tags::MASK + 1`
+    fn compress<Tags: PtrList>(&self, ptr: CopyPtrUnion<Tags>) -> CompressedPtrUnion<Tags, Self> {
+        let (ptr, tag) = ptr.split();
+        let offset = addr(ptr).get().checked_sub(addr(self.base()).get());
+
+        let offset = match offset.and_then(|offset| u32::try_from(offset).ok()) {
+            Some(offset) => offset,
+            None => panic!("the pointer is outside of the 32-bit range of this arena"),
+        };
+
+        CompressedPtrUnion::from_raw(offset, tag)
+    }
+
+    /// Converts a compressed handle back into a union of references
+    ///
+    /// # Panics
+    ///
+    /// if the base of the arena isn't aligned to at least `Tags::MASK + 1`
+    ///
+    /// # Safety
+    ///
+    /// `ptr` must have come from [`compress`](Self::compress) on this arena (or on one with
+    /// the same base), and the references must still be valid
+    unsafe fn resolve<Tags: PtrList>(
+        &self,
+        ptr: CompressedPtrUnion<Tags, Self>,
+    ) -> CopyPtrUnion<Tags> {
+        let (offset, tag) = ptr.split();
+        // we must use wrapping_add here so that we get the provenance of the arena
+        let ptr = self.base().as_ptr().wrapping_add(offset as usize);
+        let ptr = unsafe { NonNull::new_unchecked(ptr.cast()) };
+
+        // this panics if the base isn't aligned enough for the tag
+        unsafe { CopyPtrUnion::<Tags>::from_raw(RawPtrUnion::from_raw(ptr, tag)) }
+    }
+}
+
+unsafe impl<T> Arena for [T] {
+    fn base(&self) -> NonNull<u8> {
+        NonNull::from(self).cast()
+    }
+}
+
+/// A union of references into an [`Arena`], stored as a tagged 32-bit offset from the
+/// base of the arena
+///
+/// The tag is stored in the low bits of the offset like [`LowBits`], and the handle
+/// only means something together with the arena it was compressed against, so comparisons
+/// and hashing use the offset and tag rather than the targets. Use [`Arena::resolve`] to get
+/// a [`CopyPtrUnion`] back.
+pub struct CompressedPtrUnion<Tags, Base: ?Sized> {
+    raw: u32,
+    _ty: PhantomData<(Tags, fn() -> *const Base)>,
+}
+
+impl<Tags, Base: ?Sized> Copy for CompressedPtrUnion<Tags, Base> {}
+impl<Tags, Base: ?Sized> Clone for CompressedPtrUnion<Tags, Base> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<Tags: PtrList, Base: ?Sized> CompressedPtrUnion<Tags, Base> {
+    const fn from_raw(offset: u32, tag: u8) -> Self {
+        assert!(tag < Tags::LEN, "invalid tag");
+        assert!(
+            offset as usize & Tags::MASK == 0,
+            "Invalid pointer alignment for this PtrUnion"
+        );
+
+        Self {
+            raw: offset | tag as u32,
+            _ty: PhantomData,
+        }
+    }
+
+    const fn split(&self) -> (u32, u8) {
+        let tag = (self.raw as usize & Tags::MASK) as u8;
+
+        if tag >= Tags::LEN {
+            unsafe { core::hint::unreachable_unchecked() }
+        }
+
+        (self.raw - tag as u32, tag)
+    }
+
+    pub fn tag(&self) -> usize {
+        self.split().1 as usize
+    }
+
+    pub fn is<P: ErasablePtr, N: Peano>(&self) -> bool
+    where
+        Tags: Access<P, N>,
+    {
+        self.split().1 == N::VALUE
+    }
+
+    /// The offset of the pointer from the base of the arena
+    pub fn offset(&self) -> u32 {
+        self.split().0
+    }
+
+    pub fn try_to_superset<NewTags, Ns>(
+        self,
+    ) -> Result<CompressedPtrUnion<NewTags, Base>, InvalidAlignment>
+    where
+        Tags: SubsetOf<NewTags, Ns>,
+        NewTags: PtrList,
+    {
+        if self.offset() as usize & NewTags::MASK == 0 {
+            Ok(self.to_superset())
+        } else {
+            Err(InvalidAlignment)
+        }
+    }
+
+    pub fn to_superset<NewTags, Ns>(self) -> CompressedPtrUnion<NewTags, Base>
+    where
+        Tags: SubsetOf<NewTags, Ns>,
+        NewTags: PtrList,
+    {
+        let (offset, tag) = self.split();
+        CompressedPtrUnion::from_raw(offset, unsafe { Tags::map_tag_to_superset(tag) })
+    }
+
+    pub fn reorganize<NewTags, Ns>(self) -> CompressedPtrUnion<NewTags, Base>
+    where
+        Tags: SubsetOf<NewTags, Ns, Remaining = Nil>,
+        NewTags: PtrList,
+    {
+        self.to_superset()
+    }
+
+    pub fn try_to_subset<NewTags, Ns>(
+        self,
+    ) -> Result<CompressedPtrUnion<NewTags, Base>, CompressedPtrUnion<NewTags::Remaining, Base>>
+    where
+        NewTags: SubsetOf<Tags, Ns>,
+    {
+        let (offset, tag) = self.split();
+
+        // the mask will always be smaller in a subset
+        match unsafe { NewTags::try_map_tag_to_subset(tag, tag) } {
+            Ok(tag) => Ok(CompressedPtrUnion::from_raw(offset, tag)),
+            Err(new_tag) => Err(CompressedPtrUnion::from_raw(offset, new_tag)),
+        }
+    }
+}
+
+impl<Tags, Base: ?Sized> PartialEq for CompressedPtrUnion<Tags, Base> {
+    fn eq(&self, other: &Self) -> bool {
+        self.raw == other.raw
+    }
+}
+
+impl<Tags, Base: ?Sized> Eq for CompressedPtrUnion<Tags, Base> {}
+
+impl<Tags, Base: ?Sized> core::hash::Hash for CompressedPtrUnion<Tags, Base> {
+    fn hash<H: core::hash::Hasher>(&self, state: &mut H) {
+        self.raw.hash(state)
+    }
+}
@@ -0,0 +1,338 @@
+use super::*;
+
+/// How a [`PtrUnion`] stores its pointer and tag
+///
+/// This is implemented for every [`PackedEncoding`], which stores the tag inside the pointer
+/// using a [`RawPtrUnion`], and for [`Wide`], which stores the tag next to the pointer.
+///
+/// # Safety
+///
+/// This trait is sealed. The unions rely on `split` returning exactly the pointer and tag
+/// that were passed to `from_raw`, with a tag less than `Tags::LEN`, and on every pointer
+/// that is accepted for a list also being accepted for every list which is at most as long.
+pub unsafe trait TagEncoding<Tags: PtrList>: sealed::SealedEncoding {
+    type Raw: Copy;
+
+    /// Splits the raw union into the untagged pointer and the tag
+    fn split(raw: &Self::Raw) -> (NonNull<()>, u8);
+
+    /// Tags the pointer with `tag`, or returns an error if the tag can't be stored
+    /// alongside this pointer
+    ///
+    /// # Panics
+    ///
+    /// if `tag >= Tags::LEN`
+    fn try_from_raw(ptr: NonNull<()>, tag: u8) -> Result<Self::Raw, InvalidAlignment>;
+
+    /// Tags the pointer with `tag`
+    ///
+    /// # Panics
+    ///
+    /// if `tag >= Tags::LEN`, or if the tag can't be stored alongside this pointer
+    fn from_raw(ptr: NonNull<()>, tag: u8) -> Self::Raw;
+
+    /// # Safety
+    ///
+    /// `tag` must be less than `Tags::LEN`, and the tag must be storable alongside this pointer
+    unsafe fn from_raw_unchecked(ptr: NonNull<()>, tag: u8) -> Self::Raw;
+
+    /// The user flag bits stored alongside the tag, encodings without flags always return zero
+    fn flags(_raw: &Self::Raw) -> usize {
+        0
+    }
+
+    /// Replaces the user flag bits stored alongside the tag
+    ///
+    /// # Panics
+    ///
+    /// if the flags don't fit in the encoding, encodings without flags only accept zero
+    fn with_flags(raw: Self::Raw, flags: usize) -> Self::Raw {
+        assert!(
+            flags == 0,
+            "the flags don't fit in the spare bits of this PtrUnion"
+        );
+        raw
+    }
+
+    /// Checks if the two unions have the same pointer and tag
+    fn ptr_eq(this: &Self::Raw, other: &Self::Raw) -> bool {
+        Self::split(this) == Self::split(other)
+    }
+
+    /// Hashes the pointer and tag, consistent with [`ptr_eq`](Self::ptr_eq)
+    fn ptr_hash<S: core::hash::Hasher>(this: &Self::Raw, state: &mut S) {
+        let (ptr, tag) = Self::split(this);
+        core::ptr::hash(ptr.as_ptr(), state);
+        state.write_u8(tag);
+    }
+
+    /// # Safety
+    ///
+    /// See [`RawPtrUnion::map_any`]
+    unsafe fn map_any<F: MapperOutput>(raw: &Self::Raw, f: F) -> F::Output
+    where
+        Tags: Map<F>,
+    {
+        let (ptr, tag) = Self::split(raw);
+        Tags::map(ptr, tag, f)
+    }
+
+    /// # Safety
+    ///
+    /// See [`RawPtrUnion::map_hash`]
+    unsafe fn map_hash<S: core::hash::Hasher>(raw: &Self::Raw, state: &mut S)
+    where
+        Tags: MapHash,
+    {
+        let (ptr, tag) = Self::split(raw);
+        state.write_u8(tag);
+        Tags::map_hash(ptr, tag, state)
+    }
+
+    /// # Safety
+    ///
+    /// See [`RawPtrUnion::unpack`]
+    unsafe fn unpack(raw: Self::Raw) -> Tags {
+        let (ptr, tag) = <Self as TagEncoding<Tags>>::split(&raw);
+        unsafe { Tags::into_inner(ptr, tag) }
+    }
+
+    /// # Safety
+    ///
+    /// See [`RawPtrUnion::drop_in_place`]
+    unsafe fn drop_in_place(raw: Self::Raw) {
+        let (ptr, tag) = <Self as TagEncoding<Tags>>::split(&raw);
+        unsafe { Tags::drop_at(ptr, tag) }
+    }
+
+    /// Re-tags the union for the superset `NewTags`, or returns an error if the
+    /// larger tag can't be stored alongside the pointer
+    ///
+    /// All of the conversions keep the flags.
+    fn try_to_superset<NewTags, Ns>(
+        raw: <Self as TagEncoding<Tags>>::Raw,
+    ) -> Result<<Self as TagEncoding<NewTags>>::Raw, InvalidAlignment>
+    where
+        Tags: SubsetOf<NewTags, Ns>,
+        NewTags: PtrList,
+        Self: TagEncoding<NewTags>,
+    {
+        let (ptr, tag) = <Self as TagEncoding<Tags>>::split(&raw);
+
+        let tag = unsafe { Tags::map_tag_to_superset(tag) };
+
+        let flags = <Self as TagEncoding<Tags>>::flags(&raw);
+        <Self as TagEncoding<NewTags>>::try_from_raw(ptr, tag)
+            .map(|new| <Self as TagEncoding<NewTags>>::with_flags(new, flags))
+    }
+
+    /// Re-tags the union for the superset `NewTags`
+    ///
+    /// # Panics
+    ///
+    /// if the larger tag can't be stored alongside the pointer
+    fn to_superset<NewTags, Ns>(
+        raw: <Self as TagEncoding<Tags>>::Raw,
+    ) -> <Self as TagEncoding<NewTags>>::Raw
+    where
+        Tags: SubsetOf<NewTags, Ns>,
+        NewTags: PtrList,
+        Self: TagEncoding<NewTags>,
+    {
+        let (ptr, tag) = <Self as TagEncoding<Tags>>::split(&raw);
+
+        let tag = unsafe { Tags::map_tag_to_superset(tag) };
+
+        let new = <Self as TagEncoding<NewTags>>::from_raw(ptr, tag);
+        <Self as TagEncoding<NewTags>>::with_flags(new, <Self as TagEncoding<Tags>>::flags(&raw))
+    }
+
+    /// Re-tags the union for `NewTags`, which has the same pointer types in a different order
+    fn reorganize<NewTags, Ns>(
+        raw: <Self as TagEncoding<Tags>>::Raw,
+    ) -> <Self as TagEncoding<NewTags>>::Raw
+    where
+        Tags: SubsetOf<NewTags, Ns, Remaining = Nil>,
+        NewTags: PtrList,
+        Self: TagEncoding<NewTags>,
+    {
+        assert_eq!(NewTags::LEN, Tags::LEN);
+
+        let (ptr, tag) = <Self as TagEncoding<Tags>>::split(&raw);
+
+        let tag = unsafe { Tags::map_tag_to_superset(tag) };
+
+        // the new tags have exactly the same number of elements as the current tags
+        let new = unsafe { <Self as TagEncoding<NewTags>>::from_raw_unchecked(ptr, tag) };
+        <Self as TagEncoding<NewTags>>::with_flags(new, <Self as TagEncoding<Tags>>::flags(&raw))
+    }
+
+    /// Re-tags the union for the subset `NewTags` if the active variant is in `NewTags`,
+    /// otherwise re-tags it for the remaining pointer types
+    #[allow(clippy::type_complexity)]
+    fn try_to_subset<NewTags, Ns>(
+        raw: <Self as TagEncoding<Tags>>::Raw,
+    ) -> Result<<Self as TagEncoding<NewTags>>::Raw, <Self as TagEncoding<NewTags::Remaining>>::Raw>
+    where
+        NewTags: SubsetOf<Tags, Ns>,
+        Self: TagEncoding<NewTags> + TagEncoding<NewTags::Remaining>,
+    {
+        let (ptr, tag) = <Self as TagEncoding<Tags>>::split(&raw);
+        let flags = <Self as TagEncoding<Tags>>::flags(&raw);
+
+        match unsafe { NewTags::try_map_tag_to_subset(tag, tag) } {
+            // subsets are always shorter than the full list
+            Ok(tag) => {
+                let new = unsafe { <Self as TagEncoding<NewTags>>::from_raw_unchecked(ptr, tag) };
+                Ok(<Self as TagEncoding<NewTags>>::with_flags(new, flags))
+            }
+            Err(new_tag) => {
+                let new = unsafe {
+                    <Self as TagEncoding<NewTags::Remaining>>::from_raw_unchecked(ptr, new_tag)
+                };
+                Err(<Self as TagEncoding<NewTags::Remaining>>::with_flags(
+                    new, flags,
+                ))
+            }
+        }
+    }
+}
+
+/// A [`TagEncoding`] which packs the tag into the pointer of a [`RawPtrUnion`]
+///
+/// The tag is shifted left by `TAG_SHIFT` and added to the pointer, and every bit of
+/// `TAG_MASK` and `FLAG_MASK` must be zero in the untagged pointer. The tag is added with
+/// `wrapping_add`, and removed with `wrapping_sub`, so the provenance of the pointer is preserved.
+///
+/// # Safety
+///
+/// This trait is sealed, it is only implemented for [`LowBits`], [`FlagBits`] and [`HighBits`].
+/// The unions rely on the `TAG_MASK` of a sublist being a subset of the `TAG_MASK`
+/// of the full list, on `TAG_SHIFT` and `FLAG_MASK` being the same for every list,
+/// and on `FLAG_MASK` not overlapping `TAG_MASK`.
+pub unsafe trait PackedEncoding<Tags: PtrList>: sealed::SealedEncoding {
+    /// The bits of the address which hold the tag
+    const TAG_MASK: usize;
+    /// The position of the lowest bit of the tag
+    const TAG_SHIFT: u32;
+    /// The bits of the address which hold user flags, see [`FlagBits`]
+    const FLAG_MASK: usize = 0;
+}
+
+macro_rules! packed_encoding {
+    (impl[$($generics:tt)*] $name:ty) => {
+        unsafe impl<$($generics)*> TagEncoding<Tags> for $name {
+            type Raw = RawPtrUnion<Tags, $name>;
+
+            fn split(raw: &Self::Raw) -> (NonNull<()>, u8) {
+                raw.split()
+            }
+
+            fn try_from_raw(ptr: NonNull<()>, tag: u8) -> Result<Self::Raw, InvalidAlignment> {
+                RawPtrUnion::try_from_raw(ptr, tag)
+            }
+
+            fn from_raw(ptr: NonNull<()>, tag: u8) -> Self::Raw {
+                RawPtrUnion::from_raw(ptr, tag)
+            }
+
+            unsafe fn from_raw_unchecked(ptr: NonNull<()>, tag: u8) -> Self::Raw {
+                unsafe { RawPtrUnion::from_raw_unchecked(ptr, tag) }
+            }
+
+            fn flags(raw: &Self::Raw) -> usize {
+                raw.flags()
+            }
+
+            fn with_flags(raw: Self::Raw, flags: usize) -> Self::Raw {
+                raw.with_flags(flags)
+            }
+
+            fn ptr_eq(this: &Self::Raw, other: &Self::Raw) -> bool {
+                RawPtrUnion::ptr_eq(this, other)
+            }
+
+            fn ptr_hash<S: core::hash::Hasher>(this: &Self::Raw, state: &mut S) {
+                RawPtrUnion::ptr_hash(this, state)
+            }
+        }
+    };
+}
+
+/// Stores the tag in the low bits of the pointer, which are always zero for aligned pointers
+///
+/// This is the default encoding, and requires every pointer to be aligned to at least
+/// `Tags::MASK + 1`, so 8-byte aligned pointers can store at most 8 variants.
+pub enum LowBits {}
+
+impl sealed::SealedEncoding for LowBits {}
+
+unsafe impl<Tags: PtrList> PackedEncoding<Tags> for LowBits {
+    const TAG_MASK: usize = Tags::MASK;
+    const TAG_SHIFT: u32 = 0;
+}
+
+packed_encoding!(impl[Tags: PtrList] LowBits);
+
+/// Stores the tag in the low bits of the pointer like [`LowBits`], and `N` user flag bits
+/// below it
+///
+/// The flags take the lowest `N` bits and the tag is shifted above them, so every pointer
+/// must be aligned to at least `(Tags::MASK + 1) << N`. This is checked at compile time
+/// from the alignment of the targets, so a list without enough spare bits fails to compile
+/// when the union is constructed.
+pub enum FlagBits<const N: usize> {}
+
+impl<const N: usize> sealed::SealedEncoding for FlagBits<N> {}
+
+/// The number of low bits which are zero in every pointer of the list
+pub(crate) const fn spare_bits<Tags: AlignedList + ?Sized>() -> u32 {
+    let mut spare = usize::BITS;
+    let mut i = 0;
+    while i < Tags::LEN as usize {
+        if (Tags::ALIGN_BITS[i] as u32) < spare {
+            spare = Tags::ALIGN_BITS[i] as u32;
+        }
+        i += 1;
+    }
+
+    spare
+}
+
+unsafe impl<Tags: AlignedList, const N: usize> PackedEncoding<Tags> for FlagBits<N> {
+    const TAG_MASK: usize = {
+        assert!(
+            N as u32 + Tags::MASK.count_ones() <= spare_bits::<Tags>(),
+            "the pointers in this list don't have enough spare bits for the tag and the flags"
+        );
+
+        Tags::MASK << N
+    };
+    const TAG_SHIFT: u32 = N as u32;
+    const FLAG_MASK: usize = (1 << N) - 1;
+}
+
+packed_encoding!(impl[Tags: AlignedList, const N: usize] FlagBits<N>);
+
+cfg_high_bits! {
+    /// Stores the tag in the upper 16 bits of the pointer, which are unused by user-space
+    /// addresses on x86_64 Linux
+    ///
+    /// This works regardless of the alignment of the pointers, so it supports up to 255
+    /// variants of any pointer type. It assumes that user-space addresses fit in 48 bits,
+    /// constructing a union from an address which uses the upper 16 bits (for example with
+    /// 5-level paging and an explicit `mmap` hint) panics.
+    ///
+    /// This is not available on aarch64, where top-byte-ignore and memory tagging give
+    /// valid pointers a nonzero top byte.
+    pub enum HighBits {}
+
+    impl sealed::SealedEncoding for HighBits {}
+
+    unsafe impl<Tags: PtrList> PackedEncoding<Tags> for HighBits {
+        const TAG_MASK: usize = 0xffff << 48;
+        const TAG_SHIFT: u32 = 48;
+    }
+
+    packed_encoding!(impl[Tags: PtrList] HighBits);
+}
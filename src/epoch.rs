@@ -0,0 +1,242 @@
+//! Epoch-based reclamation for [`AtomicPtrUnion`]
+//!
+//! Readers [`pin`] the current epoch for as long as they use a value loaded from an
+//! [`AtomicPtrUnion`]. When a value is replaced it is retired with the current epoch, and
+//! it is only dropped (with [`PtrList::drop_at`]) once the global epoch has advanced twice
+//! since then. The epoch can only advance when no guard is pinned to the previous epoch,
+//! so no reader which could have loaded the retired value is still active by then.
+//!
+//! Retired values are collected whenever a value is retired, or by calling [`collect`].
+//! Values which are still waiting when the program exits are leaked.
+
+use super::*;
+
+use core::ops::Deref;
+use core::sync::atomic::{AtomicPtr, AtomicUsize, Ordering};
+use std::sync::Mutex;
+
+static EPOCH: AtomicUsize = AtomicUsize::new(0);
+// the number of guards pinned to an even and to an odd epoch
+static PINNED: [AtomicUsize; 2] = [AtomicUsize::new(0), AtomicUsize::new(0)];
+static RETIRED: Mutex<Vec<Retired>> = Mutex::new(Vec::new());
+
+/// A value which was replaced in the epoch `epoch`, and is waiting to be dropped
+struct Retired {
+    ptr: NonNull<()>,
+    tag: u8,
+    drop_at: unsafe fn(NonNull<()>, u8),
+    epoch: usize,
+}
+
+// only pointer lists which are `Send` are retired
+unsafe impl Send for Retired {}
+
+/// Keeps every value loaded while it is alive from being dropped
+///
+/// A guard should be short-lived, since no retired value can be dropped while it exists.
+pub struct Guard {
+    parity: usize,
+}
+
+impl Drop for Guard {
+    fn drop(&mut self) {
+        PINNED[self.parity].fetch_sub(1, Ordering::Release);
+    }
+}
+
+/// Pins the current epoch until the returned guard is dropped
+pub fn pin() -> Guard {
+    loop {
+        let epoch = EPOCH.load(Ordering::SeqCst);
+        let parity = epoch % 2;
+        PINNED[parity].fetch_add(1, Ordering::SeqCst);
+
+        // the epoch may have advanced before we were counted, then we need to try again
+        if EPOCH.load(Ordering::SeqCst) == epoch {
+            return Guard { parity };
+        }
+
+        PINNED[parity].fetch_sub(1, Ordering::Release);
+    }
+}
+
+fn lock_retired() -> std::sync::MutexGuard<'static, Vec<Retired>> {
+    RETIRED.lock().unwrap_or_else(|poison| poison.into_inner())
+}
+
+/// Advances the epoch if no guard is pinned to the previous epoch, and drops every
+/// retired value which can no longer be reached by a reader
+pub fn collect() {
+    let epoch = EPOCH.load(Ordering::SeqCst);
+
+    if PINNED[(epoch + 1) % 2].load(Ordering::SeqCst) == 0 {
+        let _ = EPOCH.compare_exchange(epoch, epoch + 1, Ordering::SeqCst, Ordering::SeqCst);
+    }
+
+    let epoch = EPOCH.load(Ordering::SeqCst);
+    let ready = {
+        let mut retired = lock_retired();
+        let (ready, waiting) = retired
+            .drain(..)
+            .partition::<Vec<_>, _>(|retired| retired.epoch + 2 <= epoch);
+        *retired = waiting;
+        ready
+    };
+
+    // dropping may retire more values, so this must be done without holding the lock
+    for retired in ready {
+        unsafe { (retired.drop_at)(retired.ptr, retired.tag) }
+    }
+}
+
+/// # Safety
+///
+/// `raw` must own its pointer, and must not be reachable by any reader which starts
+/// after this call
+unsafe fn retire<Tags: PtrList + Send + 'static>(raw: RawPtrUnion<Tags>) {
+    let (ptr, tag) = raw.split();
+
+    lock_retired().push(Retired {
+        ptr,
+        tag,
+        drop_at: Tags::drop_at,
+        epoch: EPOCH.load(Ordering::SeqCst),
+    });
+
+    collect()
+}
+
+/// A [`PtrUnion`] which can be loaded and replaced from many threads, without cloning
+/// the active pointer
+///
+/// Unlike [`AtomicSharedPtrUnion`] this works with any owned pointer type like `Box`,
+/// readers borrow the value through a [`Guard`], and replaced values are dropped once no
+/// guard which could have loaded them is alive, see the [module docs](self). Replaced
+/// values may be dropped by any thread at any later time, so replacing a value requires
+/// the pointer list to be `Send + 'static`.
+pub struct AtomicPtrUnion<Tags: PtrList> {
+    ptr: AtomicPtr<u8>,
+    _ty: PhantomData<PtrUnion<Tags>>,
+}
+
+unsafe impl<Tags: PtrList + Send> Send for AtomicPtrUnion<Tags> {}
+// values are borrowed by many threads, and dropped by whichever thread collects them
+unsafe impl<Tags: PtrList + Send + Sync> Sync for AtomicPtrUnion<Tags> {}
+
+/// A value loaded from an [`AtomicPtrUnion`], which derefs to the union
+pub struct Loaded<'g, Tags: PtrList> {
+    ptr: core::mem::ManuallyDrop<PtrUnion<Tags>>,
+    _guard: PhantomData<&'g Guard>,
+}
+
+impl<Tags: PtrList> Deref for Loaded<'_, Tags> {
+    type Target = PtrUnion<Tags>;
+
+    fn deref(&self) -> &PtrUnion<Tags> {
+        &self.ptr
+    }
+}
+
+impl<Tags: PtrList> Drop for AtomicPtrUnion<Tags> {
+    fn drop(&mut self) {
+        // the loaded values borrow `self`, so there can't be any readers left
+        drop(Self::from_bits(*self.ptr.get_mut()))
+    }
+}
+
+impl<Tags: PtrList> From<PtrUnion<Tags>> for AtomicPtrUnion<Tags> {
+    fn from(ptr: PtrUnion<Tags>) -> Self {
+        Self::new(ptr)
+    }
+}
+
+impl<Tags: PtrList> AtomicPtrUnion<Tags> {
+    fn into_bits(ptr: PtrUnion<Tags>) -> *mut u8 {
+        ptr.into_raw().into_tagged().as_ptr()
+    }
+
+    fn raw(bits: *mut u8) -> RawPtrUnion<Tags> {
+        // only non-null pointers from `into_bits` are ever stored
+        unsafe { RawPtrUnion::from_tagged(NonNull::new_unchecked(bits)) }
+    }
+
+    /// Takes ownership of the pointer stored in `bits`
+    fn from_bits(bits: *mut u8) -> PtrUnion<Tags> {
+        unsafe { PtrUnion::<Tags>::from_raw(Self::raw(bits)) }
+    }
+
+    fn borrow<'g>(bits: *mut u8) -> Loaded<'g, Tags> {
+        Loaded {
+            ptr: core::mem::ManuallyDrop::new(Self::from_bits(bits)),
+            _guard: PhantomData,
+        }
+    }
+
+    pub fn new(ptr: PtrUnion<Tags>) -> Self {
+        Self {
+            ptr: AtomicPtr::new(Self::into_bits(ptr)),
+            _ty: PhantomData,
+        }
+    }
+
+    pub fn into_inner(self) -> PtrUnion<Tags> {
+        let this = core::mem::ManuallyDrop::new(self);
+        Self::from_bits(this.ptr.load(Ordering::Relaxed))
+    }
+
+    /// Borrows the current value until the guard is dropped
+    pub fn load<'g>(&'g self, _guard: &'g Guard) -> Loaded<'g, Tags> {
+        Self::borrow(self.ptr.load(Ordering::SeqCst))
+    }
+
+    /// Replaces the current value, the previous value is dropped once no reader can
+    /// be using it
+    pub fn store(&self, ptr: PtrUnion<Tags>)
+    where
+        Tags: Send + 'static,
+    {
+        let old = self.ptr.swap(Self::into_bits(ptr), Ordering::SeqCst);
+        unsafe { retire(Self::raw(old)) }
+    }
+
+    /// Replaces the current value and borrows the previous value until the guard is
+    /// dropped, after that it is dropped once no reader can be using it
+    pub fn swap<'g>(&'g self, ptr: PtrUnion<Tags>, _guard: &'g Guard) -> Loaded<'g, Tags>
+    where
+        Tags: Send + 'static,
+    {
+        let old = self.ptr.swap(Self::into_bits(ptr), Ordering::SeqCst);
+        // the guard keeps the retired value alive while it is borrowed
+        unsafe { retire(Self::raw(old)) };
+        Self::borrow(old)
+    }
+
+    /// Stores `new` if the current value has the same pointer and tag as `current`, and
+    /// borrows the previous value like [`swap`](Self::swap), otherwise gives `new` back
+    pub fn compare_exchange<'g>(
+        &'g self,
+        current: &PtrUnion<Tags>,
+        new: PtrUnion<Tags>,
+        _guard: &'g Guard,
+    ) -> Result<Loaded<'g, Tags>, PtrUnion<Tags>>
+    where
+        Tags: Send + 'static,
+    {
+        let (ptr, tag) = current.split();
+        let current = unsafe { RawPtrUnion::<Tags>::from_raw_unchecked(ptr, tag) };
+        let new = Self::into_bits(new);
+
+        match self.ptr.compare_exchange(
+            current.into_tagged().as_ptr(),
+            new,
+            Ordering::SeqCst,
+            Ordering::SeqCst,
+        ) {
+            Ok(old) => {
+                unsafe { retire(Self::raw(old)) };
+                Ok(Self::borrow(old))
+            }
+            Err(_) => Err(Self::from_bits(new)),
+        }
+    }
+}
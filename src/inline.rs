@@ -0,0 +1,84 @@
+use super::*;
+
+/// A small integer stored in the pointer bits of a union instead of behind a pointer
+///
+/// `Inline<T>` acts like a pointer to a `T` in a pointer list, so it can be mixed with
+/// real pointers in [`TypeList!`], and [`map_any`](PtrUnion::map_any), `Eq` and `Hash`
+/// see a `&T`. Nothing is allocated, the value is stored in bits 16 and up of the address,
+/// which leaves the low 15 bits free for the tag and the upper 16 bits unused for
+/// [`HighBits`](crate::HighBits).
+///
+/// The low 15 bits are reported through [`DerefRaw::ALIGN_BITS`], so [`Prefix`] and
+/// [`FlagBits`] can use them for this variant.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub struct Inline<T>(pub T);
+
+/// An integer type which fits in the address bits used by [`Inline`]
+///
+/// # Safety
+///
+/// This trait is sealed, it is implemented for the integer types of at most 16 bits,
+/// and for the 32-bit integers on 64-bit targets
+pub unsafe trait InlineInt: Copy + sealed::SealedInt {
+    #[doc(hidden)]
+    fn to_bits(self) -> usize;
+
+    #[doc(hidden)]
+    fn from_bits(bits: usize) -> Self;
+}
+
+macro_rules! inline_int {
+    ($($(#[$meta:meta])* $ty:ty => $unsigned:ty;)*) => {$(
+        $(#[$meta])*
+        impl sealed::SealedInt for $ty {}
+
+        $(#[$meta])*
+        unsafe impl InlineInt for $ty {
+            fn to_bits(self) -> usize {
+                self as $unsigned as usize
+            }
+
+            fn from_bits(bits: usize) -> Self {
+                bits as $unsigned as $ty
+            }
+        }
+    )*};
+}
+
+inline_int! {
+    u8 => u8;
+    i8 => u8;
+    u16 => u16;
+    i16 => u16;
+    #[cfg(target_pointer_width = "64")]
+    u32 => u32;
+    #[cfg(target_pointer_width = "64")]
+    i32 => u32;
+}
+
+const VALUE_SHIFT: u32 = 16;
+// keeps the address non-null even when the value is zero
+const MARKER: usize = 1 << (VALUE_SHIFT - 1);
+
+unsafe impl<T: InlineInt> ErasablePtr for Inline<T> {
+    fn into_raw(this: Self) -> NonNull<()> {
+        let addr = this.0.to_bits() << VALUE_SHIFT | MARKER;
+        // the marker bit is always set, so the address is never zero
+        unsafe { NonNull::new_unchecked(core::ptr::without_provenance_mut(addr)) }
+    }
+
+    unsafe fn from_raw(ptr: NonNull<()>) -> Self {
+        Inline(T::from_bits(addr(ptr).get() >> VALUE_SHIFT))
+    }
+}
+
+unsafe impl<T: InlineInt> DerefRaw for Inline<T> {
+    type Target = T;
+
+    // the marker is the bit just below the value, so every bit below the marker is clear
+    const ALIGN_BITS: u8 = (VALUE_SHIFT - 1) as u8;
+
+    unsafe fn with_target<R>(ptr: NonNull<()>, f: impl FnOnce(&Self::Target) -> R) -> R {
+        f(&unsafe { Self::from_raw(ptr) }.0)
+    }
+}
@@ -1,9 +1,32 @@
 use std::cmp::Ordering;
 
-use thin_ptr::Erasable;
-
 use super::*;
 
+pub(crate) mod sealed {
+    #[diagnostic::on_unimplemented(
+        message = "`{Self}` is not a pointer list",
+        label = "pointer lists can only be built with `TypeList!`"
+    )]
+    pub trait SealedList {}
+    pub trait SealedPeano {}
+    pub trait SealedEncoding {}
+    pub trait SealedAlignment {}
+    pub trait SealedInt {}
+    pub trait SealedAccess<T, N> {}
+    pub trait SealedSubset<Ts, Ns> {}
+}
+
+impl sealed::SealedList for Nil {}
+impl<T, Ts> sealed::SealedList for Cons<T, Ts> {}
+impl sealed::SealedPeano for Z {}
+impl<T> sealed::SealedPeano for S<T> {}
+// `Access` and `SubsetOf` take type parameters, so without their own seals a downstream
+// crate could implement them for a `Cons` at one of its own types
+impl<T, Ts> sealed::SealedAccess<T, Z> for Cons<T, Ts> {}
+impl<T, Ts, U, N> sealed::SealedAccess<U, S<N>> for Cons<T, Ts> {}
+impl<Ts> sealed::SealedSubset<Ts, Nil> for Nil {}
+impl<T, Ts, Us, N, Ns> sealed::SealedSubset<Us, Cons<N, Ns>> for Cons<T, Ts> {}
+
 pub enum Nil {}
 pub enum Cons<T, Ts> {
     Current(T),
@@ -13,11 +36,19 @@ pub enum Cons<T, Ts> {
 pub struct Z;
 pub struct S<T>(T);
 
-pub unsafe trait PtrVariants {
+/// # Safety
+///
+/// This trait is sealed and cannot be implemented outside of this crate
+pub unsafe trait PtrVariants: sealed::SealedList {
     const LEN: usize;
 }
 
-pub unsafe trait Peano {
+/// A type-level natural number, used to index into a pointer list
+///
+/// # Safety
+///
+/// This trait is sealed, it is only implemented for [`Z`] and [`S`]
+pub unsafe trait Peano: sealed::SealedPeano {
     const VALUE: u8;
 }
 
@@ -28,12 +59,26 @@ unsafe impl Peano for Z {
     const VALUE: u8 = 0;
 }
 
-pub unsafe trait Access<T, N>: PtrList {
+/// The pointer list contains the pointer type `T` at index `N`
+///
+/// # Safety
+///
+/// This trait is sealed, it is only implemented for [`Cons`]
+#[diagnostic::on_unimplemented(
+    message = "the pointer type `{T}` is not a variant of the pointer list `{Self}`",
+    label = "`{T}` is not in this pointer list",
+    note = "add `{T}` to the `TypeList!` of this union, or convert it to one of the listed pointer types"
+)]
+pub unsafe trait Access<T, N>: PtrList + sealed::SealedAccess<T, N> {
     type Remaining: PtrList;
 
+    /// # Safety
+    ///
+    /// `ptr` must have come from `T::into_raw`, ownership is transferred to the list
     unsafe fn new(ptr: NonNull<()>) -> Self;
 }
 
+#[diagnostic::do_not_recommend]
 unsafe impl<T: ErasablePtr, Ts: PtrList> Access<T, Z> for Cons<T, Ts> {
     type Remaining = Ts;
 
@@ -42,6 +87,7 @@ unsafe impl<T: ErasablePtr, Ts: PtrList> Access<T, Z> for Cons<T, Ts> {
     }
 }
 
+#[diagnostic::do_not_recommend]
 unsafe impl<T: ErasablePtr, Ts: Access<U, N>, U, N: Peano> Access<U, S<N>> for Cons<T, Ts> {
     type Remaining = Cons<T, Ts::Remaining>;
 
@@ -50,14 +96,30 @@ unsafe impl<T: ErasablePtr, Ts: Access<U, N>, U, N: Peano> Access<U, S<N>> for C
     }
 }
 
-pub unsafe trait SubsetOf<Ts: PtrList, Ns>: PtrList {
+/// Every pointer type in this list is also in `Ts`, at the indices `Ns`
+///
+/// # Safety
+///
+/// This trait is sealed, it is only implemented for [`Nil`] and [`Cons`]
+#[diagnostic::on_unimplemented(
+    message = "the pointer list `{Self}` is not a subset of `{Ts}`",
+    label = "not every pointer type of `{Self}` is in `{Ts}`"
+)]
+pub unsafe trait SubsetOf<Ts: PtrList, Ns>: PtrList + sealed::SealedSubset<Ts, Ns> {
     type Remaining: PtrList;
 
+    /// # Safety
+    ///
+    /// `tag` must be less than `Self::LEN`
     unsafe fn map_tag_to_superset(tag: u8) -> u8;
 
+    /// # Safety
+    ///
+    /// `tag` and `new_tag` must be less than `Ts::LEN`
     unsafe fn try_map_tag_to_subset(tag: u8, new_tag: u8) -> Result<u8, u8>;
 }
 
+#[diagnostic::do_not_recommend]
 unsafe impl<Ts: PtrList> SubsetOf<Ts, Nil> for Nil {
     type Remaining = Ts;
 
@@ -70,6 +132,7 @@ unsafe impl<Ts: PtrList> SubsetOf<Ts, Nil> for Nil {
     }
 }
 
+#[diagnostic::do_not_recommend]
 unsafe impl<T: ErasablePtr, Ts: PtrList, Us, N: Peano, Ns> SubsetOf<Us, Cons<N, Ns>> for Cons<T, Ts>
 where
     Us: Access<T, N>,
@@ -79,7 +142,9 @@ where
 
     unsafe fn map_tag_to_superset(tag: u8) -> u8 {
         if let Some(tag) = tag.checked_sub(1) {
-            Ts::map_tag_to_superset(tag)
+            // `Ts` is indexed in `Us::Remaining`, which doesn't have `T` at `N`
+            let tag = unsafe { Ts::map_tag_to_superset(tag) };
+            tag + u8::from(N::VALUE <= tag)
         } else {
             N::VALUE
         }
@@ -89,20 +154,37 @@ where
         if N::VALUE == tag {
             Ok(0)
         } else {
-            Ts::try_map_tag_to_subset(tag, new_tag - u8::from(N::VALUE < new_tag)).map(|x| x + 1)
+            let tag = tag - u8::from(N::VALUE < tag);
+            let new_tag = new_tag - u8::from(N::VALUE < new_tag);
+            unsafe { Ts::try_map_tag_to_subset(tag, new_tag) }.map(|x| x + 1)
         }
     }
 }
 
-pub unsafe trait PtrList {
+/// A type-level list of pointer types, built with [`TypeList!`]
+///
+/// To use a custom pointer type in a list implement [`ErasablePtr`] for it.
+///
+/// # Safety
+///
+/// This trait is sealed, it is only implemented for [`Nil`] and [`Cons`].
+/// The unions rely on `LEN` and `MASK` being exact, so custom lists aren't supported.
+pub unsafe trait PtrList: sealed::SealedList {
     const LEN: u8;
     const MASK: usize = {
         let len = Self::LEN as usize;
         len.next_power_of_two().wrapping_sub(1)
     };
 
+    /// # Safety
+    ///
+    /// `tag` must be less than `Self::LEN`, and `ptr` must have come from `into_raw`
+    /// of the pointer type at index `tag`. Ownership of `ptr` is transferred to the list
     unsafe fn into_inner(ptr: NonNull<()>, tag: u8) -> Self;
 
+    /// # Safety
+    ///
+    /// The same as [`into_inner`](Self::into_inner)
     unsafe fn drop_at(ptr: NonNull<()>, tag: u8);
 }
 
@@ -138,114 +220,513 @@ unsafe impl<T: ErasablePtr, Ts: PtrList> PtrList for Cons<T, Ts> {
     }
 }
 
+/// A pointer type whose target can be borrowed from the pointer returned by `into_raw`
+///
+/// This is implemented for every pointer which derefs to an [`Erasable`] target, and for
+/// [`Inline`] values, which don't point to anything. Operations on the targets of a union,
+/// like [`map_any`](PtrUnion::map_any), `Eq` and `Hash`, go through this trait.
+///
+/// Unions are built from pointers with [`into_union_raw`](Self::into_union_raw) instead of
+/// `into_raw`. For pointers to zero-sized targets the blanket impl replaces the dangling
+/// pointer (whose address is the alignment of the target, often 1) with a dangling pointer
+/// which has room for any tag, so `Box<()>` and friends can be stored in a union.
+/// This only helps encodings which check the address, like [`LowBits`]. Encodings which
+/// rely on [`AlignedList`], like [`FlagBits`] and [`Prefix`], still only see the alignment
+/// of the target, because a reference or an `Rc` to a zero-sized target can have any
+/// address. Give the target enough alignment there, like `#[repr(align(8))] struct Marker;`.
+///
+/// # Safety
+///
+/// `from_raw` must accept any pointer returned by `into_union_raw`, and the low
+/// [`ALIGN_BITS`](Self::ALIGN_BITS) bits of those pointers must be zero.
+///
+/// `with_target` must only read from `ptr` as much as `from_raw` could, and must pass `f` a
+/// reference to a value which stays valid and unmodified until `f` returns. The value may
+/// be a temporary owned by `with_target`, like the integer of an [`Inline`], so callers
+/// must not keep the reference past the call. Only the blanket impl lends out the target
+/// in place, which [`PtrUnion::get`] relies on.
+pub unsafe trait DerefRaw: ErasablePtr {
+    type Target: ?Sized;
+
+    /// The number of low bits which are zero in every pointer from `into_union_raw`, on top
+    /// of those implied by the alignment of the target, this is used by [`AlignedList`]
+    const ALIGN_BITS: u8 = 0;
+
+    /// Converts the pointer into the raw pointer stored in a union, `into_raw` by default
+    fn into_union_raw(this: Self) -> NonNull<()>
+    where
+        Self: Sized,
+    {
+        Self::into_raw(this)
+    }
+
+    /// Calls `f` with a reference to the target of `ptr`
+    ///
+    /// # Safety
+    ///
+    /// `ptr` must have come from `into_raw` of this pointer type, and it must be valid to
+    /// create a shared reference to its target for the duration of this call
+    unsafe fn with_target<R>(ptr: NonNull<()>, f: impl FnOnce(&Self::Target) -> R) -> R;
+}
+
+unsafe impl<P> DerefRaw for P
+where
+    P: ErasablePtr + core::ops::Deref,
+    P::Target: Erasable,
+{
+    type Target = P::Target;
+
+    fn into_union_raw(this: Self) -> NonNull<()> {
+        let size = core::mem::size_of_val::<P::Target>(&this);
+        let align = core::mem::align_of_val::<P::Target>(&this);
+        let ptr = P::into_raw(this);
+        let dangling = crate::__sentinel_ptr();
+
+        // any aligned pointer is a valid pointer to a zero-sized value, so it doesn't
+        // need to be restored in `from_raw`
+        if size == 0 && addr(ptr).get() == align && align < addr(dangling).get() {
+            dangling
+        } else {
+            ptr
+        }
+    }
+
+    unsafe fn with_target<R>(ptr: NonNull<()>, f: impl FnOnce(&Self::Target) -> R) -> R {
+        let ptr = unsafe { <P::Target>::unerase(ptr) };
+        f(unsafe { ptr.as_ref() })
+    }
+}
+
 pub trait MapperOutput {
     type Output;
-    const IS_PROPERTY: bool = false;
 }
 
+/// An operation that can be applied to the active variant of a union with `map_any`
+///
+/// This is the supported way to add new operations on unions,
+/// implement it for every target type in the pointer list.
+#[diagnostic::on_unimplemented(
+    message = "the mapper `{Self}` can't be applied to a `{T}`",
+    label = "`{Self}` doesn't implement `Mapper<{T}>`"
+)]
 pub trait Mapper<T: ?Sized>: MapperOutput {
     fn call(self, value: &T) -> Self::Output;
 }
 
-pub unsafe trait MapHash {
+/// A type-level predicate, `P: Predicate<T>` means that `T` satisfies `P`
+///
+/// See [`pred`] for the predicates provided by this crate
+pub trait Predicate<T: ?Sized> {}
+
+/// Predicates that mirror the standard library traits
+pub mod pred {
+    use super::Predicate;
+
+    macro_rules! predicates {
+        ($($(#[$meta:meta])* $name:ident($($bound:tt)+);)*) => {$(
+            $(#[$meta])*
+            pub struct $name;
+
+            impl<T: $($bound)+> Predicate<T> for $name {}
+        )*};
+    }
+
+    predicates! {
+        /// satisfied by types that implement [`Send`](core::marker::Send)
+        Send(?Sized + core::marker::Send);
+        /// satisfied by types that implement [`Sync`](core::marker::Sync)
+        Sync(?Sized + core::marker::Sync);
+        /// satisfied by types that implement [`Copy`](core::marker::Copy)
+        Copy(core::marker::Copy);
+        /// satisfied by types that implement [`Clone`](core::clone::Clone)
+        Clone(core::clone::Clone);
+        /// satisfied by types that implement [`PartialEq`](core::cmp::PartialEq)
+        PartialEq(?Sized + core::cmp::PartialEq);
+        /// satisfied by types that implement [`Eq`](core::cmp::Eq)
+        Eq(?Sized + core::cmp::Eq);
+        /// satisfied by types that implement [`PartialOrd`](core::cmp::PartialOrd)
+        PartialOrd(?Sized + core::cmp::PartialOrd);
+        /// satisfied by types that implement [`Ord`](core::cmp::Ord)
+        Ord(?Sized + core::cmp::Ord);
+        /// satisfied by types that implement [`Hash`](core::hash::Hash)
+        Hash(?Sized + core::hash::Hash);
+        /// satisfied by types that implement [`Debug`](core::fmt::Debug)
+        Debug(?Sized + core::fmt::Debug);
+    }
+}
+
+/// Every pointer in the list satisfies the predicate `P`
+pub trait AllPointers<P>: PtrList {}
+
+impl<P> AllPointers<P> for Nil {}
+impl<P, T, Ts> AllPointers<P> for Cons<T, Ts>
+where
+    T: ErasablePtr,
+    Ts: AllPointers<P>,
+    P: Predicate<T>,
+{
+}
+
+/// The target of every pointer in the list satisfies the predicate `P`
+pub trait AllTargets<P>: PtrList {}
+
+impl<P> AllTargets<P> for Nil {}
+impl<P, T, Ts> AllTargets<P> for Cons<T, Ts>
+where
+    T: DerefRaw,
+    Ts: AllTargets<P>,
+    P: Predicate<T::Target>,
+{
+}
+
+/// Every pointer in the list is [`Copy`]
+pub trait AllCopy: AllPointers<pred::Copy> {}
+impl<Ts: AllPointers<pred::Copy>> AllCopy for Ts {}
+
+/// Every pointer in the list is [`Clone`]
+pub trait AllClone: AllPointers<pred::Clone> {}
+impl<Ts: AllPointers<pred::Clone>> AllClone for Ts {}
+
+/// A pointer list where the alignment and size of every target is known, used by [`Prefix`]
+/// and [`RelPtrUnion::validate`]
+///
+/// This is implemented for every list of pointers which deref to a sized target.
+///
+/// # Safety
+///
+/// This trait is sealed, it is only implemented for [`Nil`] and [`Cons`]
+pub unsafe trait AlignedList: PtrList {
+    /// The number of low bits which are zero in an aligned pointer, for each pointer
+    /// type in the list, followed by zeros
+    const ALIGN_BITS: [u8; 256];
+
+    /// The size of the target of each pointer type in the list, followed by zeros
+    const SIZES: [usize; 256];
+
+    /// Whether every pointer in the list has enough zero low bits to store the tag with
+    /// [`LowBits`], so the list fits in a one-word [`PtrUnion`] instead of a [`WidePtrUnion`]
+    const FITS_LOW_BITS: bool = Self::MASK.count_ones() <= encoding::spare_bits::<Self>();
+}
+
+unsafe impl AlignedList for Nil {
+    const ALIGN_BITS: [u8; 256] = [0; 256];
+    const SIZES: [usize; 256] = [0; 256];
+}
+
+unsafe impl<T, Ts: AlignedList> AlignedList for Cons<T, Ts>
+where
+    T: DerefRaw,
+    T::Target: Sized,
+{
+    const ALIGN_BITS: [u8; 256] = {
+        let mut bits = [0; 256];
+        bits[0] = core::mem::align_of::<T::Target>().trailing_zeros() as u8;
+        if T::ALIGN_BITS > bits[0] {
+            bits[0] = T::ALIGN_BITS;
+        }
+
+        let mut i = 0;
+        while i < Ts::LEN as usize {
+            bits[i + 1] = Ts::ALIGN_BITS[i];
+            i += 1;
+        }
+
+        bits
+    };
+
+    const SIZES: [usize; 256] = {
+        let mut sizes = [0; 256];
+        sizes[0] = core::mem::size_of::<T::Target>();
+
+        let mut i = 0;
+        while i < Ts::LEN as usize {
+            sizes[i + 1] = Ts::SIZES[i];
+            i += 1;
+        }
+
+        sizes
+    };
+}
+
+/// # Safety
+///
+/// This trait is sealed, it is only implemented for [`Nil`] and [`Cons`]
+#[diagnostic::on_unimplemented(
+    message = "the targets of the pointer list `{Self}` can't be hashed",
+    label = "not every target of `{Self}` implements `Hash`",
+    note = "every pointer type in the list must deref to a target that implements `Hash`"
+)]
+pub unsafe trait MapHash: sealed::SealedList {
+    /// # Safety
+    ///
+    /// `tag` must be less than the length of the list, and `ptr` must be valid to
+    /// create a shared reference to the target of the pointer type at index `tag`
     unsafe fn map_hash<S: core::hash::Hasher>(ptr: NonNull<()>, tag: u8, state: &mut S);
 }
 
+#[diagnostic::do_not_recommend]
 unsafe impl MapHash for Nil {
     unsafe fn map_hash<S: core::hash::Hasher>(_ptr: NonNull<()>, _tag: u8, _state: &mut S) {
         unsafe { core::hint::unreachable_unchecked() }
     }
 }
 
+#[diagnostic::do_not_recommend]
 unsafe impl<T, Ts> MapHash for Cons<T, Ts>
 where
-    T: ErasablePtr + core::ops::Deref,
+    T: DerefRaw,
     Ts: MapHash,
-    T::Target: Erasable + core::hash::Hash,
+    T::Target: core::hash::Hash,
 {
     unsafe fn map_hash<S: core::hash::Hasher>(ptr: NonNull<()>, tag: u8, state: &mut S) {
         if let Some(tag) = tag.checked_sub(1) {
             Ts::map_hash(ptr, tag, state)
         } else {
-            let ptr = <T::Target>::unerase(ptr);
-            core::hash::Hash::hash(ptr.as_ref(), state)
+            T::with_target(ptr, |value| core::hash::Hash::hash(value, state))
         }
     }
 }
 
-pub unsafe trait Map<F: MapperOutput> {
+/// Clones the active pointer of a pointer list
+///
+/// # Safety
+///
+/// This trait is sealed, it is only implemented for [`Nil`] and [`Cons`]
+#[diagnostic::on_unimplemented(
+    message = "the pointer list `{Self}` can't be cloned",
+    label = "not every pointer type of `{Self}` implements `Clone`",
+    note = "every pointer type in the list must implement `Clone`"
+)]
+pub unsafe trait MapClone: sealed::SealedList {
+    /// # Safety
+    ///
+    /// `tag` must be less than the length of the list, and `ptr` must have come from
+    /// `into_raw` of the pointer type at index `tag` and still be valid for that pointer type
+    unsafe fn clone_at(ptr: NonNull<()>, tag: u8) -> NonNull<()>;
+}
+
+#[diagnostic::do_not_recommend]
+unsafe impl MapClone for Nil {
+    unsafe fn clone_at(_ptr: NonNull<()>, _tag: u8) -> NonNull<()> {
+        unsafe { core::hint::unreachable_unchecked() }
+    }
+}
+
+#[diagnostic::do_not_recommend]
+unsafe impl<T, Ts> MapClone for Cons<T, Ts>
+where
+    T: DerefRaw + Clone,
+    Ts: MapClone,
+{
+    unsafe fn clone_at(ptr: NonNull<()>, tag: u8) -> NonNull<()> {
+        if let Some(tag) = tag.checked_sub(1) {
+            Ts::clone_at(ptr, tag)
+        } else {
+            let ptr = core::mem::ManuallyDrop::new(T::from_raw(ptr));
+            T::into_union_raw(T::clone(&ptr))
+        }
+    }
+}
+
+/// A pointer list of references which can be reborrowed for the shorter lifetime `'a`,
+/// see [`PtrUnion::reborrow`] and [`PtrUnion::as_refs`]
+///
+/// # Safety
+///
+/// Every pointer type in `Reborrowed` and `Shared` must point to the same target type as the
+/// pointer type at the same index in this list
+pub unsafe trait Reborrow<'a>: PtrList {
+    /// The list with every `&mut T` replaced by `&'a mut T`, and every `&T` by `&'a T`
+    type Reborrowed: PtrList;
+    /// The list with every reference replaced by `&'a T`
+    type Shared: PtrList;
+}
+
+unsafe impl<'a> Reborrow<'a> for Nil {
+    type Reborrowed = Nil;
+    type Shared = Nil;
+}
+
+unsafe impl<'a, 'b: 'a, T: 'a, Ts: Reborrow<'a>> Reborrow<'a> for Cons<&'b mut T, Ts> {
+    type Reborrowed = Cons<&'a mut T, Ts::Reborrowed>;
+    type Shared = Cons<&'a T, Ts::Shared>;
+}
+
+unsafe impl<'a, 'b: 'a, T: 'a, Ts: Reborrow<'a>> Reborrow<'a> for Cons<&'b T, Ts> {
+    type Reborrowed = Cons<&'a T, Ts::Reborrowed>;
+    type Shared = Cons<&'a T, Ts::Shared>;
+}
+
+/// A pointer list where every pointer type derefs to its target, so the union can be
+/// borrowed as a union of references, see [`PtrUnion::as_ref_union`]
+///
+/// # Safety
+///
+/// `Refs` must have a reference to the target of the pointer type at each index in this
+/// list, which is implemented for the same pointers as the blanket impl of [`DerefRaw`]
+pub unsafe trait AsRefList<'a>: PtrList {
+    /// The list with every pointer type `P` replaced by `&'a P::Target`
+    type Refs: PtrList;
+}
+
+unsafe impl<'a> AsRefList<'a> for Nil {
+    type Refs = Nil;
+}
+
+unsafe impl<'a, T, Ts> AsRefList<'a> for Cons<T, Ts>
+where
+    T: ErasablePtr + core::ops::Deref,
+    T::Target: Erasable + 'a,
+    Ts: AsRefList<'a>,
+{
+    type Refs = Cons<&'a T::Target, Ts::Refs>;
+}
+
+/// A pointer list of boxes and `'static` references, which can be leaked into a list of
+/// `'static` references, see [`PtrUnion::leak`]
+///
+/// # Safety
+///
+/// `Leaked` must have a `'static` reference to the target of the pointer type at each index
+/// in this list, and it must be valid to forget the pointers in this list
+pub unsafe trait LeakList: PtrList {
+    /// The list with every `Box<T>` replaced by `&'static T`
+    type Leaked: PtrList;
+}
+
+unsafe impl LeakList for Nil {
+    type Leaked = Nil;
+}
+
+unsafe impl<T: Erasable + 'static, Ts: LeakList> LeakList for Cons<Box<T>, Ts> {
+    type Leaked = Cons<&'static T, Ts::Leaked>;
+}
+
+unsafe impl<T: Erasable + 'static, Ts: LeakList> LeakList for Cons<&'static T, Ts> {
+    type Leaked = Cons<&'static T, Ts::Leaked>;
+}
+
+/// Applies the mapper `F` to the active variant of a pointer list
+///
+/// # Safety
+///
+/// This trait is sealed, it is only implemented for [`Nil`] and [`Cons`].
+/// To add new operations implement [`Mapper`] instead.
+#[diagnostic::on_unimplemented(
+    message = "the mapper `{F}` can't be applied to every target of the pointer list `{Self}`",
+    label = "`{F}` doesn't implement `Mapper` for every target of `{Self}`",
+    note = "`map_any` calls the mapper on whichever variant is active, so it must accept the target of every pointer in the list"
+)]
+pub unsafe trait Map<F: MapperOutput>: sealed::SealedList {
+    /// # Safety
+    ///
+    /// The same as [`MapHash::map_hash`]
     unsafe fn map(ptr: NonNull<()>, tag: u8, f: F) -> F::Output;
 }
 
+#[diagnostic::do_not_recommend]
 unsafe impl<F: MapperOutput> Map<F> for Nil {
     unsafe fn map(_ptr: NonNull<()>, _tag: u8, _f: F) -> <F as MapperOutput>::Output {
         unsafe { core::hint::unreachable_unchecked() }
     }
 }
 
+#[diagnostic::do_not_recommend]
 unsafe impl<T, Ts, F> Map<F> for Cons<T, Ts>
 where
-    T: ErasablePtr + core::ops::Deref,
+    T: DerefRaw,
     Ts: Map<F>,
     F: Mapper<T::Target>,
-    T::Target: Erasable,
 {
     unsafe fn map(ptr: NonNull<()>, tag: u8, f: F) -> <F as MapperOutput>::Output {
         if let Some(tag) = tag.checked_sub(1) {
             Ts::map(ptr, tag, f)
         } else {
-            let ptr = <T::Target>::unerase(ptr);
-            f.call(ptr.as_ref())
+            T::with_target(ptr, |value| f.call(value))
         }
     }
 }
 
-pub struct PartialEqAny(pub(crate) NonNull<()>);
+/// An operation on the targets of two unions with the same active variant
+#[diagnostic::on_unimplemented(
+    message = "the pair mapper `{Self}` can't be applied to a `{T}`",
+    label = "`{Self}` doesn't implement `PairMapper<{T}>`"
+)]
+pub trait PairMapper<T: ?Sized>: MapperOutput {
+    fn call(self, a: &T, b: &T) -> Self::Output;
+}
 
-impl MapperOutput for PartialEqAny {
-    type Output = bool;
+/// Applies the pair mapper `F` to the active variant of two pointer lists with the same tag
+///
+/// # Safety
+///
+/// This trait is sealed, it is only implemented for [`Nil`] and [`Cons`]
+#[diagnostic::on_unimplemented(
+    message = "the pair mapper `{F}` can't be applied to every target of the pointer list `{Self}`",
+    label = "`{F}` doesn't implement `PairMapper` for every target of `{Self}`"
+)]
+pub unsafe trait MapPair<F: MapperOutput>: sealed::SealedList {
+    /// # Safety
+    ///
+    /// The same as [`MapHash::map_hash`], for both `a` and `b`
+    unsafe fn map_pair(a: NonNull<()>, b: NonNull<()>, tag: u8, f: F) -> F::Output;
 }
 
-impl<T: ?Sized + Erasable + PartialEq> Mapper<T> for PartialEqAny {
-    fn call(self, value: &T) -> Self::Output {
-        let ptr = unsafe { T::unerase(self.0) };
-        value == unsafe { ptr.as_ref() }
+#[diagnostic::do_not_recommend]
+unsafe impl<F: MapperOutput> MapPair<F> for Nil {
+    unsafe fn map_pair(_a: NonNull<()>, _b: NonNull<()>, _tag: u8, _f: F) -> F::Output {
+        unsafe { core::hint::unreachable_unchecked() }
     }
 }
 
-pub struct EqAny;
+#[diagnostic::do_not_recommend]
+unsafe impl<T, Ts, F> MapPair<F> for Cons<T, Ts>
+where
+    T: DerefRaw,
+    Ts: MapPair<F>,
+    F: PairMapper<T::Target>,
+{
+    unsafe fn map_pair(a: NonNull<()>, b: NonNull<()>, tag: u8, f: F) -> F::Output {
+        if let Some(tag) = tag.checked_sub(1) {
+            Ts::map_pair(a, b, tag, f)
+        } else {
+            T::with_target(a, |a| T::with_target(b, |b| f.call(a, b)))
+        }
+    }
+}
+
+pub struct PartialEqAny;
 
-impl MapperOutput for EqAny {
-    type Output = ();
-    const IS_PROPERTY: bool = true;
+impl MapperOutput for PartialEqAny {
+    type Output = bool;
 }
 
-impl<T: ?Sized + Eq> Mapper<T> for EqAny {
-    fn call(self, _value: &T) -> Self::Output {}
+impl<T: ?Sized + PartialEq> PairMapper<T> for PartialEqAny {
+    fn call(self, a: &T, b: &T) -> Self::Output {
+        a == b
+    }
 }
 
-pub struct PartialOrdAny(pub(crate) NonNull<()>);
+pub struct PartialOrdAny;
 
 impl MapperOutput for PartialOrdAny {
     type Output = Option<Ordering>;
 }
 
-impl<T: ?Sized + Erasable + PartialOrd> Mapper<T> for PartialOrdAny {
-    fn call(self, value: &T) -> Self::Output {
-        let ptr = unsafe { T::unerase(self.0) };
-        value.partial_cmp(unsafe { ptr.as_ref() })
+impl<T: ?Sized + PartialOrd> PairMapper<T> for PartialOrdAny {
+    fn call(self, a: &T, b: &T) -> Self::Output {
+        a.partial_cmp(b)
     }
 }
 
-pub struct OrdAny(pub(crate) NonNull<()>);
+pub struct OrdAny;
 
 impl MapperOutput for OrdAny {
     type Output = Ordering;
 }
 
-impl<T: ?Sized + Erasable + Ord> Mapper<T> for OrdAny {
-    fn call(self, value: &T) -> Self::Output {
-        let ptr = unsafe { T::unerase(self.0) };
-        value.cmp(unsafe { ptr.as_ref() })
+impl<T: ?Sized + Ord> PairMapper<T> for OrdAny {
+    fn call(self, a: &T, b: &T) -> Self::Output {
+        a.cmp(b)
     }
 }
@@ -2,16 +2,58 @@
 
 use std::{marker::PhantomData, num::NonZeroUsize, ptr::NonNull};
 
-use thin_ptr::ErasablePtr;
+pub use thin_ptr::{Erasable, ErasablePtr};
 
-mod copy_ptr_union;
+/// Only compiles the items on targets which support [`HighBits`], where user-space
+/// addresses fit in 48 bits and leave the upper 16 bits of a pointer unused
+macro_rules! cfg_high_bits {
+    ($($item:item)*) => {$(
+        #[cfg(all(target_os = "linux", target_arch = "x86_64"))]
+        $item
+    )*};
+}
+
+mod aligned;
+mod atomic;
+mod compressed;
+mod encoding;
+pub mod epoch;
+mod inline;
+mod locked;
+mod once;
+mod ownership;
+mod prefix_ptr_union;
 mod ptr_union;
 mod raw_ptr_union;
+mod rel_ptr_union;
+mod sentinel;
+mod versioned;
+mod wide_ptr_union;
 
-use raw_ptr_union::*;
+pub use aligned::{Align, Aligned, AlignedArc, AlignedBox, Alignment};
+pub use atomic::{AtomicCopyPtrUnion, AtomicOptionCopyPtrUnion, AtomicSharedPtrUnion};
+pub use compressed::{Arena, CompressedPtrUnion};
+pub use epoch::AtomicPtrUnion;
+pub use raw_ptr_union::RawPtrUnion;
 
-pub use copy_ptr_union::CopyPtrUnion;
-pub use ptr_union::PtrUnion;
+pub use encoding::{FlagBits, LowBits, PackedEncoding, TagEncoding};
+pub use inline::{Inline, InlineInt};
+pub use locked::{LockedPtrUnion, LockedPtrUnionGuard};
+pub use once::OncePtrUnion;
+pub use ownership::{Clones, Copied, Holds, Owned, OwnedRaw, Ownership, Shared};
+pub use prefix_ptr_union::{Prefix, PrefixPtrUnion, RawPrefixPtrUnion};
+pub use ptr_union::{CopyPtrUnion, PtrUnion, PtrUnionWithFlags, SharedPtrUnion};
+pub use rel_ptr_union::{InvalidRelPtr, RelPtrUnion};
+#[doc(hidden)]
+pub use sentinel::{__sentinel_ptr, __SENTINEL_ALIGN_BITS};
+pub use sentinel::{Empty, Tombstone};
+pub use versioned::VersionedPtr;
+pub use wide_ptr_union::{RawWidePtrUnion, Wide, WidePtrUnion};
+
+cfg_high_bits! {
+    pub use encoding::HighBits;
+    pub use ptr_union::HighBitsPtrUnion;
+}
 
 mod interface;
 pub use interface::*;
@@ -61,3 +103,895 @@ fn test_not_included_in_subset() {
 
     assert!(core::ptr::eq(&a, y));
 }
+
+#[test]
+fn test_subset_out_of_order() {
+    let b = 0u64;
+    let x = CopyPtrUnion::<TypeList![&u32, &i32, &mut i32, &u64]>::new(&b);
+    let y: CopyPtrUnion<TypeList![&u32, &u64]> = x.try_to_subset().ok().unwrap();
+    assert!(y.is::<&u64, _>());
+    let y: CopyPtrUnion<TypeList![&i32, &u64, &u32]> = y.to_superset();
+    assert!(y.is::<&u64, _>());
+}
+
+#[test]
+fn test_into_inner() {
+    use std::rc::Rc;
+
+    let a = Rc::new(1u8);
+    let x = PtrUnion::<TypeList![Rc<u8>]>::new(a.clone());
+    let b = x.into_inner();
+    assert_eq!(Rc::strong_count(&a), 2);
+    drop(b);
+    assert_eq!(Rc::strong_count(&a), 1);
+
+    let x = WidePtrUnion::<TypeList![Rc<u8>]>::new(a.clone());
+    let b = x.into_inner();
+    assert_eq!(Rc::strong_count(&a), 2);
+    drop(b);
+    assert_eq!(Rc::strong_count(&a), 1);
+}
+
+#[test]
+fn test_predicates() {
+    fn all_eq<Ts: AllTargets<pred::Eq>>() {}
+    fn all_send<Ts: AllPointers<pred::Send>>() {}
+    fn all_copy<Ts: AllCopy>() {}
+
+    all_eq::<TypeList![&u32, Box<i32>, &u8]>();
+    all_send::<TypeList![Box<u32>, std::sync::Arc<u8>]>();
+    all_copy::<TypeList![&u32, &i32]>();
+
+    let a = 1u32;
+    let b = 1u32;
+    let x = CopyPtrUnion::<TypeList![&u32, &i32]>::new(&a);
+    let y = CopyPtrUnion::<TypeList![&u32, &i32]>::new(&b);
+    assert!(x == y);
+    assert_eq!(x.cmp(&y), core::cmp::Ordering::Equal);
+}
+
+#[test]
+fn test_ownership_policies() {
+    use std::rc::Rc;
+
+    let a = Rc::new(10u32);
+
+    let x = SharedPtrUnion::<TypeList![Rc<u32>, Rc<i32>]>::new(a.clone());
+    let y = x.clone();
+    assert_eq!(Rc::strong_count(&a), 3);
+    assert!(x == y);
+
+    let z: Rc<u32> = y.try_cast().unwrap();
+    assert!(Rc::ptr_eq(&a, &z));
+    assert_eq!(Rc::strong_count(&a), 4);
+
+    drop((x, y, z));
+    assert_eq!(Rc::strong_count(&a), 1);
+
+    let x = PtrUnion::<TypeList![Rc<u32>, Box<i32>]>::new(a.clone());
+    assert_eq!(Rc::strong_count(&a), 2);
+    let x: PtrUnion<TypeList![Box<i32>, Box<u8>, Rc<u32>]> = x.to_superset();
+    let x = x.take::<Rc<u32>, _>().ok().unwrap();
+    assert_eq!(Rc::strong_count(&a), 2);
+    drop(x);
+    assert_eq!(Rc::strong_count(&a), 1);
+}
+
+cfg_high_bits! {
+    #[test]
+    fn test_high_bits() {
+        type Bytes = TypeList![
+            Box<[u8; 1]>,
+            Box<[u8; 2]>,
+            Box<[u8; 3]>,
+            Box<[u8; 4]>,
+            Box<[u8; 5]>,
+            Box<[u8; 6]>,
+            Box<[u8; 7]>,
+            Box<[u8; 8]>,
+            Box<[u8; 9]>,
+            Box<[u8; 10]>,
+            Box<[u8; 11]>,
+            Box<[u8; 12]>,
+            Box<[u8; 13]>,
+            Box<[u8; 14]>,
+            Box<[u8; 15]>,
+            Box<[u8; 16]>,
+            Box<[u8; 17]>,
+            Box<[u8; 18]>,
+            Box<[u8; 19]>,
+            Box<[u8; 20]>,
+        ];
+
+        let x = HighBitsPtrUnion::<Bytes>::new(Box::new([7u8; 19]));
+        assert_eq!(x.tag(), 18);
+        assert!(x.is::<Box<[u8; 19]>, _>());
+        assert!(x == HighBitsPtrUnion::<Bytes>::new(Box::new([7u8; 19])));
+
+        let y = HighBitsPtrUnion::<Bytes>::new(Box::new([1u8; 3]));
+        assert_eq!(y.tag(), 2);
+        assert!(x != y);
+
+        let x = x.take::<Box<[u8; 19]>, _>().ok().unwrap();
+        assert_eq!(*x, [7u8; 19]);
+    }
+}
+
+#[test]
+fn test_wide() {
+    use std::rc::Rc;
+
+    type Bytes = TypeList![Box<u8>, Box<[u8; 2]>, Box<[u8; 3]>];
+
+    assert_eq!(
+        core::mem::size_of::<WidePtrUnion<Bytes>>(),
+        2 * core::mem::size_of::<usize>()
+    );
+    const { assert!(!<Bytes as AlignedList>::FITS_LOW_BITS) };
+    const { assert!(<TypeList![Box<u16>, Box<u32>] as AlignedList>::FITS_LOW_BITS) };
+    const { assert!(!<TypeList![Box<u16>, Box<u32>, Box<u64>] as AlignedList>::FITS_LOW_BITS) };
+
+    let x = WidePtrUnion::<Bytes>::new(Box::new([1u8, 2]));
+    assert_eq!(x.tag(), 1);
+    assert!(x == WidePtrUnion::<Bytes>::new(Box::new([1u8, 2])));
+    assert!(x < WidePtrUnion::<Bytes>::new(Box::new([0u8; 3])));
+
+    let x: WidePtrUnion<TypeList![Box<u16>, Box<[u8; 3]>, Box<u8>, Box<[u8; 2]>]> = x.to_superset();
+    assert_eq!(x.tag(), 3);
+
+    let x = x.take::<Box<[u8; 2]>, _>().ok().unwrap();
+    assert_eq!(*x, [1, 2]);
+
+    let a = Rc::new(3u8);
+    let x = WidePtrUnion::<TypeList![Rc<u8>, Box<u8>], Shared>::new(a.clone());
+    let y = x.clone();
+    assert_eq!(Rc::strong_count(&a), 3);
+    drop((x, y));
+    assert_eq!(Rc::strong_count(&a), 1);
+}
+
+#[test]
+fn test_prefix() {
+    type List = TypeList![Box<u64>, &'static u16, Box<i64>, Box<f64>, Box<[u64; 2]>];
+
+    assert_eq!(
+        core::mem::size_of::<PrefixPtrUnion<List>>(),
+        core::mem::size_of::<usize>()
+    );
+
+    static A: u16 = 3;
+    let x = PrefixPtrUnion::<List>::new(&A);
+    assert_eq!(x.tag(), 1);
+    assert!(x == PrefixPtrUnion::<List>::new(&A));
+
+    let y = PrefixPtrUnion::<List>::new(Box::new([7u64, 8]));
+    assert_eq!(y.tag(), 4);
+    assert!(x != y);
+    assert_eq!(*y.take::<Box<[u64; 2]>, _>().ok().unwrap(), [7, 8]);
+
+    let x = PrefixPtrUnion::<TypeList![&u16, Box<u64>, Box<u32>]>::new(Box::new(1u32));
+    let x: PrefixPtrUnion<TypeList![Box<u32>, Box<u64>, &u16]> = x.reorganize();
+    assert!(x.is::<Box<u32>, _>());
+    assert_eq!(*x.take::<Box<u32>, _>().ok().unwrap(), 1);
+}
+
+#[test]
+fn test_flags() {
+    type List = TypeList![Box<u64>, Box<u32>];
+
+    let mut x = PtrUnionWithFlags::<List, 1>::new(Box::new(5u64));
+    assert_eq!(x.flags(), 0);
+    x.set_flags(1);
+    assert_eq!(x.flags(), 1);
+    assert!(x.is::<Box<u64>, _>());
+    assert!(x == PtrUnionWithFlags::<List, 1>::new(Box::new(5u64)));
+
+    let x: PtrUnionWithFlags<TypeList![Box<u32>, Box<u64>], 1> = x.reorganize();
+    assert_eq!(x.flags(), 1);
+    assert_eq!(*x.take::<Box<u64>, _>().ok().unwrap(), 5);
+
+    type Rcs = TypeList![std::rc::Rc<u64>, std::rc::Rc<u32>];
+
+    let mut x = PtrUnionWithFlags::<Rcs, 1, Shared>::new(std::rc::Rc::new(5u64));
+    x.set_flags(1);
+    let y = x.clone();
+    assert_eq!(y.flags(), 1);
+    assert!(PtrUnion::ptr_eq(&x, &y));
+
+    x.set_flags(0);
+    assert!(x == y);
+    assert!(!PtrUnion::ptr_eq(&x, &y));
+}
+
+#[test]
+fn test_inline() {
+    use std::hash::{BuildHasher, RandomState};
+
+    struct Describe;
+
+    impl MapperOutput for Describe {
+        type Output = String;
+    }
+
+    impl<T: std::fmt::Debug> Mapper<T> for Describe {
+        fn call(self, value: &T) -> String {
+            format!("{value:?}")
+        }
+    }
+
+    type List = TypeList![Box<String>, Inline<u16>, Inline<i8>];
+
+    let x = PtrUnion::<List>::new(Inline(500u16));
+    assert_eq!(x.tag(), 1);
+    assert_eq!(x.addr(), 500 << 16 | 1 << 15);
+    assert_eq!(x.map_any(Describe), "500");
+    assert!(x == PtrUnion::<List>::new(Inline(500u16)));
+    assert!(x < PtrUnion::<List>::new(Inline(501u16)));
+
+    let state = RandomState::new();
+    assert_eq!(
+        state.hash_one(&x),
+        state.hash_one(PtrUnion::<List>::new(Inline(500u16)))
+    );
+
+    let y = PtrUnion::<List>::new(Inline(-3i8));
+    assert_eq!(y.map_any(Describe), "-3");
+    let y = y.take::<Box<String>, _>().err().unwrap();
+    assert_eq!(y.take::<Inline<i8>, _>().ok().unwrap(), Inline(-3));
+
+    let z = PtrUnion::<List>::new(Box::new(String::from("boxed")));
+    assert_eq!(z.map_any(Describe), "\"boxed\"");
+    assert!(x != z);
+
+    // the inline variants leave 15 low bits for the tag and flags
+    let mut w =
+        PtrUnionWithFlags::<TypeList![Box<u64>, Inline<u16>, Inline<u8>], 1>::new(Inline(7u8));
+    w.set_flags(1);
+    assert_eq!(w.flags(), 1);
+    assert_eq!(w.map_any(Describe), "7");
+    let w = w.take::<Inline<u8>, _>().ok().unwrap();
+    assert_eq!(w, Inline(7));
+}
+
+#[test]
+fn test_sentinel() {
+    use std::rc::Rc;
+
+    type Node = TypeList![Empty, Rc<u32>, Tombstone];
+
+    assert_eq!(
+        core::mem::size_of::<PtrUnion<Node>>(),
+        core::mem::size_of::<usize>()
+    );
+
+    let a = Rc::new(1u32);
+    let mut x = PtrUnion::<Node>::new(a.clone());
+    assert_eq!(Rc::strong_count(&a), 2);
+
+    x.set(Tombstone);
+    assert_eq!(Rc::strong_count(&a), 1);
+    assert!(x.is::<Tombstone, _>());
+    assert!(x == PtrUnion::<Node>::new(Tombstone));
+    assert!(x != PtrUnion::<Node>::new(Empty));
+
+    let x = x.take::<Empty, _>().err().unwrap();
+    assert_eq!(x.take::<Tombstone, _>().ok().unwrap(), Tombstone);
+
+    type Slot = TypeList![Box<u64>, Empty, Tombstone];
+
+    let mut x = PtrUnionWithFlags::<Slot, 1>::new(Tombstone);
+    x.set_flags(1);
+    assert!(x.is::<Tombstone, _>());
+    assert_eq!(x.flags(), 1);
+
+    let x = PrefixPtrUnion::<Slot>::new(Empty);
+    assert!(x.is::<Empty, _>());
+    assert!(x == PrefixPtrUnion::<Slot>::new(Empty));
+}
+
+#[test]
+fn test_zero_sized() {
+    #[derive(Debug, PartialEq, Eq, Hash)]
+    struct Marker;
+
+    type List = TypeList![Box<()>, Box<Marker>, Box<u16>, Box<u32>, Box<u64>];
+
+    let x = PtrUnion::<List>::new(Box::new(Marker));
+    assert_eq!(x.tag(), 1);
+    assert!(x == PtrUnion::<List>::new(Box::new(Marker)));
+    assert!(x != PtrUnion::<List>::new(Box::new(())));
+
+    let x = x.take::<Box<()>, _>().err().unwrap();
+    assert_eq!(*x.take::<Box<Marker>, _>().ok().unwrap(), Marker);
+
+    let x = CopyPtrUnion::<TypeList![&(), &u32, &u64, &i64, &i32]>::new(&());
+    assert!(x.is::<&(), _>());
+
+    #[repr(align(4))]
+    struct AlignedMarker;
+
+    let mut x = PtrUnionWithFlags::<TypeList![Box<AlignedMarker>, Box<u32>], 1>::new(Box::new(
+        AlignedMarker,
+    ));
+    x.set_flags(1);
+    assert!(x.is::<Box<AlignedMarker>, _>());
+}
+
+#[test]
+fn test_aligned_box() {
+    type Bytes = TypeList![
+        AlignedBox<[u8; 1], 8>,
+        AlignedBox<[u8; 2], 8>,
+        AlignedBox<[u8; 3], 8>,
+        AlignedBox<[u8; 4], 8>,
+        AlignedArc<[u8; 5], 8>,
+    ];
+
+    assert_eq!(core::mem::align_of::<Aligned<[u8; 3], 8>>(), 8);
+    assert_eq!(Bytes::ALIGN_BITS[..5], [3; 5]);
+
+    let x = PtrUnion::<Bytes>::new(Box::new(Aligned::new([1u8, 2, 3])));
+    assert_eq!(x.tag(), 2);
+    assert!(x == PtrUnion::<Bytes>::new(Box::new(Aligned::new([1u8, 2, 3]))));
+
+    let x = x.take::<AlignedBox<[u8; 3], 8>, _>().ok().unwrap();
+    assert_eq!(**x, [1, 2, 3]);
+    assert_eq!(x.len(), 3);
+}
+
+#[test]
+fn test_compressed() {
+    #[derive(Debug, PartialEq)]
+    enum Node {
+        Leaf(u32),
+        Branch(u32, u32),
+    }
+
+    type Refs<'a> = TypeList![&'a Node, &'a u64];
+
+    let nodes = [Node::Leaf(1), Node::Branch(0, 1), Node::Leaf(3)];
+    let arena: &[Node] = &nodes;
+
+    assert_eq!(core::mem::size_of::<CompressedPtrUnion<Refs, [Node]>>(), 4);
+
+    let x = arena.compress(CopyPtrUnion::<Refs>::new(&nodes[1]));
+    assert_eq!(x.offset() as usize, core::mem::size_of::<Node>());
+    assert!(x.is::<&Node, _>());
+    assert!(x == arena.compress(CopyPtrUnion::<Refs>::new(&nodes[1])));
+    assert!(x != arena.compress(CopyPtrUnion::<Refs>::new(&nodes[2])));
+
+    let x: CompressedPtrUnion<TypeList![&u8, &u64, &Node], [Node]> = x.to_superset();
+    let x: CompressedPtrUnion<Refs, [Node]> = x.try_to_subset().ok().unwrap();
+
+    let y = unsafe { arena.resolve(x) };
+    assert!(core::ptr::eq(y.take::<&Node, _>().ok().unwrap(), &nodes[1]));
+}
+
+#[test]
+fn test_rel_ptr_union() {
+    use core::mem::MaybeUninit;
+
+    type Refs = TypeList![&'static u64, &'static u32];
+
+    #[repr(C)]
+    struct Graph {
+        root: RelPtrUnion<Refs>,
+        a: u64,
+        b: u32,
+    }
+
+    let mut graph = MaybeUninit::<Graph>::uninit();
+    let ptr = graph.as_mut_ptr();
+    unsafe {
+        (&raw mut (*ptr).a).write(7);
+        (&raw mut (*ptr).b).write(9);
+        let root = &mut *(&raw mut (*ptr).root).cast::<MaybeUninit<_>>();
+        RelPtrUnion::init(root, CopyPtrUnion::<Refs>::new(&(*ptr).b));
+    }
+    let graph = unsafe { graph.assume_init() };
+    assert!(graph.root.tag() == 1);
+
+    // copy the bytes to a different address
+    let mut buffer = [0u64; 4];
+    let (before, bytes) = unsafe {
+        let dst = buffer.as_mut_ptr().cast::<u8>();
+        let graph_dst = dst.add(8);
+        core::ptr::copy_nonoverlapping(
+            (&raw const graph).cast::<u8>(),
+            graph_dst,
+            size_of::<Graph>(),
+        );
+        (
+            core::slice::from_raw_parts(dst, 4),
+            core::slice::from_raw_parts(graph_dst, size_of::<Graph>()),
+        )
+    };
+    let copy = unsafe { &*bytes.as_ptr().cast::<Graph>() };
+
+    assert_eq!(copy.root.validate(bytes), Ok(()));
+    assert_eq!(
+        copy.root.validate(&bytes[..16]),
+        Err(InvalidRelPtr::OutOfBounds)
+    );
+    assert_eq!(copy.root.validate(before), Err(InvalidRelPtr::OutOfBounds));
+    // the target is 16 bytes past the union, outside of what a pointer to the union may read
+    let b = unsafe { copy.root.get(bytes) }
+        .take::<&u32, _>()
+        .ok()
+        .unwrap();
+    assert!(core::ptr::eq(b, &copy.b));
+    assert_eq!(*b, 9);
+
+    let mut bad = [0u64; 1];
+    bad[0] = 3;
+    let bytes = unsafe { core::slice::from_raw_parts(bad.as_ptr().cast::<u8>(), 8) };
+    let root = unsafe {
+        &*bytes
+            .as_ptr()
+            .cast::<RelPtrUnion<TypeList![&u64, &u32, &u16]>>()
+    };
+    assert_eq!(root.validate(bytes), Err(InvalidRelPtr::InvalidTag));
+    bad[0] = 8 | 1;
+    let bytes = unsafe { core::slice::from_raw_parts(bad.as_ptr().cast::<u8>(), 8) };
+    let root = unsafe { &*bytes.as_ptr().cast::<RelPtrUnion<Refs>>() };
+    assert_eq!(root.validate(bytes), Err(InvalidRelPtr::OutOfBounds));
+
+    // the offset is aligned to 16 for the tag, but the target is only aligned to 8
+    #[repr(C, align(16))]
+    struct Buffer([u64; 4]);
+
+    type Bytes = TypeList![
+        &'static u8,
+        &'static u8,
+        &'static u8,
+        &'static u8,
+        &'static u8,
+        &'static u8,
+        &'static u8,
+        &'static u8,
+        &'static u8
+    ];
+
+    let buffer = Buffer([0, 16, 0, 0]);
+    let bytes = unsafe { core::slice::from_raw_parts(buffer.0.as_ptr().cast::<u8>(), 32) };
+    let root = unsafe { &*bytes[8..].as_ptr().cast::<RelPtrUnion<Bytes>>() };
+    assert_eq!(root.validate(bytes), Err(InvalidRelPtr::Misaligned));
+}
+
+#[test]
+fn test_atomic() {
+    use core::sync::atomic::Ordering;
+
+    type Refs = TypeList![&'static u64, &'static u32];
+
+    static A: u64 = 1;
+    static B: u32 = 2;
+
+    let x = AtomicCopyPtrUnion::new(CopyPtrUnion::<Refs>::new(&A));
+    assert!(x.load(Ordering::Relaxed).is::<&u64, _>());
+
+    let old = x.swap(CopyPtrUnion::new(&B), Ordering::AcqRel);
+    assert!(old.is::<&u64, _>());
+    assert!(x
+        .compare_exchange(old, old, Ordering::AcqRel, Ordering::Acquire)
+        .is_err());
+
+    let current = x.load(Ordering::Acquire);
+    assert!(x
+        .compare_exchange(current, old, Ordering::AcqRel, Ordering::Acquire)
+        .is_ok());
+
+    // flip between the variants from several threads, every update sees the result of
+    // exactly one other update, so the flips from each variant are balanced
+    let from_a = core::sync::atomic::AtomicUsize::new(0);
+    std::thread::scope(|s| {
+        for _ in 0..4 {
+            s.spawn(|| {
+                for _ in 0..1000 {
+                    let prev = x.fetch_update(Ordering::AcqRel, Ordering::Acquire, |ptr| {
+                        Some(if ptr.is::<&u64, _>() {
+                            CopyPtrUnion::new(&B)
+                        } else {
+                            CopyPtrUnion::new(&A)
+                        })
+                    });
+
+                    if prev.ok().unwrap().is::<&u64, _>() {
+                        from_a.fetch_add(1, Ordering::Relaxed);
+                    }
+                }
+            });
+        }
+    });
+    assert_eq!(from_a.into_inner(), 2000);
+    assert!(x.into_inner().is::<&u64, _>());
+
+    let y = AtomicOptionCopyPtrUnion::<Refs>::default();
+    assert!(y.load(Ordering::Relaxed).is_none());
+    y.store(Some(CopyPtrUnion::new(&B)), Ordering::Release);
+    assert!(y.take(Ordering::Acquire).unwrap().is::<&u32, _>());
+    assert!(y.into_inner().is_none());
+}
+
+#[test]
+fn test_atomic_shared() {
+    use std::sync::Arc;
+
+    type Config = TypeList![Arc<u64>, Arc<String>];
+
+    let a = Arc::new(1u64);
+    let b = Arc::new(String::from("b"));
+    let x = AtomicSharedPtrUnion::new(SharedPtrUnion::<Config>::new(a.clone()));
+
+    std::thread::scope(|s| {
+        for _ in 0..4 {
+            s.spawn(|| {
+                for _ in 0..1000 {
+                    let config = x.load();
+                    assert!(config.is::<Arc<u64>, _>() || config.is::<Arc<String>, _>());
+                }
+            });
+        }
+
+        for i in 0..1000 {
+            if i % 2 == 0 {
+                x.store(SharedPtrUnion::new(b.clone()));
+            } else {
+                x.store(SharedPtrUnion::new(a.clone()));
+            }
+        }
+    });
+
+    // the last store was `a`, every other clone has been released
+    assert_eq!(Arc::strong_count(&a), 2);
+    assert_eq!(Arc::strong_count(&b), 1);
+
+    let current = x.load();
+    let old = x.compare_exchange(&current, SharedPtrUnion::new(b.clone()));
+    assert!(old.ok().unwrap().is::<Arc<u64>, _>());
+    assert!(x
+        .compare_exchange(&current, SharedPtrUnion::new(b.clone()))
+        .is_err());
+    drop(current);
+
+    assert_eq!(Arc::strong_count(&a), 1);
+    assert_eq!(Arc::strong_count(&b), 2);
+    drop(x);
+    assert_eq!(Arc::strong_count(&b), 1);
+
+    // a pointer whose clone panics for zero
+    struct Flaky(Arc<u64>);
+
+    impl Clone for Flaky {
+        fn clone(&self) -> Self {
+            assert!(*self.0 != 0, "can't clone zero");
+            Flaky(self.0.clone())
+        }
+    }
+
+    impl core::ops::Deref for Flaky {
+        type Target = u64;
+
+        fn deref(&self) -> &u64 {
+            &self.0
+        }
+    }
+
+    unsafe impl ErasablePtr for Flaky {
+        fn into_raw(this: Self) -> NonNull<()> {
+            ErasablePtr::into_raw(this.0)
+        }
+
+        unsafe fn from_raw(ptr: NonNull<()>) -> Self {
+            Flaky(unsafe { ErasablePtr::from_raw(ptr) })
+        }
+    }
+
+    let x = AtomicSharedPtrUnion::new(SharedPtrUnion::<TypeList![Flaky, Arc<String>]>::new(Flaky(
+        Arc::new(0),
+    )));
+    let load = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| x.load()));
+    assert!(load.is_err());
+    // the panicking reader must not keep the writer waiting
+    x.store(SharedPtrUnion::new(b.clone()));
+    assert_eq!(Arc::strong_count(&b), 2);
+}
+
+#[test]
+fn test_versioned() {
+    use core::sync::atomic::Ordering;
+
+    type Refs = TypeList![&'static u64, &'static [u64; 2]];
+
+    static A: u64 = 1;
+    static B: [u64; 2] = [2, 3];
+
+    assert_eq!(VersionedPtr::<Refs>::VERSION_BITS, 2);
+
+    let x = VersionedPtr::new(CopyPtrUnion::<Refs>::new(&A));
+    let first = x.load(Ordering::Acquire);
+    assert_eq!(first.1, 0);
+
+    // A -> B -> A, the pointer is the same again but the version isn't
+    let b = x
+        .compare_exchange(
+            first,
+            CopyPtrUnion::new(&B),
+            Ordering::AcqRel,
+            Ordering::Acquire,
+        )
+        .ok()
+        .unwrap();
+    assert!(b.0.is::<&u64, _>());
+    let b = x.load(Ordering::Acquire);
+    assert_eq!(b.1, 1);
+    x.compare_exchange(
+        b,
+        CopyPtrUnion::new(&A),
+        Ordering::AcqRel,
+        Ordering::Acquire,
+    )
+    .ok()
+    .unwrap();
+
+    let stale = x.compare_exchange(
+        first,
+        CopyPtrUnion::new(&B),
+        Ordering::AcqRel,
+        Ordering::Acquire,
+    );
+    let current = stale.err().unwrap();
+    assert!(CopyPtrUnion::ptr_eq(&current.0, &first.0));
+    assert_eq!(current.1, 2);
+
+    // the version wraps around after `VERSION_BITS`
+    let current = x
+        .compare_exchange(
+            current,
+            CopyPtrUnion::new(&A),
+            Ordering::AcqRel,
+            Ordering::Acquire,
+        )
+        .ok()
+        .unwrap();
+    x.compare_exchange(
+        (current.0, 3),
+        CopyPtrUnion::new(&B),
+        Ordering::AcqRel,
+        Ordering::Acquire,
+    )
+    .ok()
+    .unwrap();
+    let (last, version) = x.into_inner();
+    assert!(last.is::<&[u64; 2], _>());
+    assert_eq!(version, 0);
+}
+
+#[test]
+fn test_locked() {
+    type Slot = TypeList![Box<u64>, Box<String>];
+
+    let x = LockedPtrUnion::new(PtrUnion::<Slot>::new(Box::new(0u64)));
+
+    std::thread::scope(|s| {
+        for _ in 0..4 {
+            s.spawn(|| {
+                for _ in 0..1000 {
+                    let mut guard = x.lock();
+                    let value = core::mem::replace(&mut *guard, PtrUnion::new(Box::new(0u64)));
+                    let mut value: Box<u64> = value.take().ok().unwrap();
+                    *value += 1;
+                    guard.set(value);
+                }
+            });
+        }
+    });
+
+    let mut guard = x.lock();
+    assert!(x.try_lock().is_none());
+    let count = core::mem::replace(&mut *guard, PtrUnion::new(Box::new(String::from("done"))));
+    assert_eq!(*count.take::<Box<u64>, _>().ok().unwrap(), 4000);
+    drop(guard);
+
+    let value: Box<String> = x.into_inner().take().ok().unwrap();
+    assert_eq!(*value, "done");
+
+    // the union behind a leaked guard is leaked too, instead of being dropped twice
+    type Counted = TypeList![std::rc::Rc<u64>, Box<u64>];
+
+    let a = std::rc::Rc::new(1u64);
+    let x = LockedPtrUnion::new(PtrUnion::<Counted>::new(a.clone()));
+    core::mem::forget(x.lock());
+    assert!(x.try_lock().is_none());
+    drop(x);
+    assert_eq!(std::rc::Rc::strong_count(&a), 2);
+
+    let x = LockedPtrUnion::new(PtrUnion::<Counted>::new(a.clone()));
+    core::mem::forget(x.lock());
+    let inner = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| x.into_inner()));
+    assert!(inner.is_err());
+    assert_eq!(std::rc::Rc::strong_count(&a), 3);
+}
+
+#[test]
+fn test_once() {
+    type Cache = TypeList![Box<u64>, Box<String>];
+
+    let x = PtrUnion::<Cache>::new(Box::new(String::from("x")));
+    assert_eq!(x.get::<Box<String>, _>().unwrap(), "x");
+    assert!(x.get::<Box<u64>, _>().is_none());
+
+    let cache = OncePtrUnion::<Cache>::new();
+    assert!(cache.get().is_none());
+
+    std::thread::scope(|s| {
+        for i in 0..4u64 {
+            let cache = &cache;
+            s.spawn(move || {
+                let value = cache.get_or_init(|| PtrUnion::new(Box::new(i)));
+                assert!(value.get::<Box<u64>, _>().is_some());
+            });
+        }
+    });
+
+    let first = *cache.get().unwrap().get::<Box<u64>, _>().unwrap();
+    assert!(first < 4);
+    assert!(cache.set(PtrUnion::new(Box::new(String::new()))).is_err());
+
+    let value = cache.into_inner().unwrap();
+    assert_eq!(value.get::<Box<u64>, _>(), Some(&first));
+}
+
+#[test]
+fn test_epoch() {
+    use core::sync::atomic::{AtomicUsize, Ordering};
+
+    static DROPPED: AtomicUsize = AtomicUsize::new(0);
+
+    struct Counted(u64);
+
+    impl Drop for Counted {
+        fn drop(&mut self) {
+            // poison the value, so that a reader which sees it after it was dropped fails
+            self.0 = u64::MAX;
+            DROPPED.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    type Node = TypeList![Box<Counted>, Box<String>];
+
+    let x = AtomicPtrUnion::new(PtrUnion::<Node>::new(Box::new(Counted(0))));
+
+    std::thread::scope(|s| {
+        for _ in 0..4 {
+            s.spawn(|| {
+                for _ in 0..1000 {
+                    let guard = epoch::pin();
+                    let value = x.load(&guard);
+                    if let Some(counted) = value.get::<Box<Counted>, _>() {
+                        assert_ne!(counted.0, u64::MAX);
+                    }
+                }
+            });
+        }
+
+        for i in 1..=1000 {
+            x.store(PtrUnion::new(Box::new(Counted(i))));
+        }
+    });
+
+    let guard = epoch::pin();
+    let old = x.swap(PtrUnion::new(Box::new(String::from("done"))), &guard);
+    assert_eq!(old.get::<Box<Counted>, _>().unwrap().0, 1000);
+    drop(guard);
+
+    // it takes two epochs before the last values can be dropped
+    while DROPPED.load(Ordering::Relaxed) < 1001 {
+        epoch::collect();
+        std::thread::yield_now();
+    }
+
+    let guard = epoch::pin();
+    let current = x.load(&guard);
+    let new = PtrUnion::new(Box::new(String::from("new")));
+    assert!(x.compare_exchange(&current, new, &guard).is_ok());
+    drop(guard);
+
+    assert_eq!(x.into_inner().get::<Box<String>, _>().unwrap(), "new");
+}
+
+#[test]
+fn test_reborrow() {
+    fn bump(x: PtrUnion<TypeList![&mut u64, &mut u32]>) {
+        match x.unpack() {
+            Cons::Current(a) => *a += 1,
+            Cons::Tail(Cons::Current(b)) => *b += 1,
+            Cons::Tail(Cons::Tail(x)) => match x {},
+        }
+    }
+
+    fn is_even(x: CopyPtrUnion<TypeList![&u64, &u32]>) -> bool {
+        match x.unpack() {
+            Cons::Current(a) => *a % 2 == 0,
+            Cons::Tail(Cons::Current(b)) => *b % 2 == 0,
+            Cons::Tail(Cons::Tail(x)) => match x {},
+        }
+    }
+
+    let mut a = 0u64;
+    let mut x = PtrUnion::<TypeList![&mut u64, &mut u32]>::new(&mut a);
+
+    bump(x.reborrow());
+    assert!(!is_even(x.as_refs()));
+    bump(x.reborrow());
+    assert!(is_even(x.as_refs()));
+    drop(x);
+
+    assert_eq!(a, 2);
+}
+
+#[test]
+fn test_as_ref_union() {
+    use std::sync::Arc;
+
+    let x = PtrUnion::<TypeList![Box<u64>, Arc<String>]>::new(Arc::new(String::from("x")));
+    let y = x.as_ref_union();
+    let z = y;
+
+    assert!(z.is::<&String, _>());
+    assert_eq!(z.tag(), x.tag());
+    let other = PtrUnion::<TypeList![Box<u64>, Arc<String>]>::new(Arc::new(String::from("x")));
+    assert!(y == other.as_ref_union());
+    assert_eq!(z.take::<&String, _>().ok().unwrap(), "x");
+
+    let mut worklist = vec![y; 3];
+    worklist.push(CopyPtrUnion::new(&1u64));
+    assert_eq!(worklist.iter().filter(|ptr| ptr.is::<&u64, _>()).count(), 1);
+}
+
+#[test]
+fn test_leak() {
+    type Expr = TypeList![Box<u64>, Box<String>, &'static u32];
+
+    let x = PtrUnion::<Expr>::new(Box::new(String::from("x")));
+    let tag = x.tag();
+    let leaked: CopyPtrUnion<TypeList![&'static u64, &'static String, &'static u32]> = x.leak();
+    assert_eq!(leaked.tag(), tag);
+
+    let copy = leaked;
+    assert_eq!(copy.take::<&String, _>().ok().unwrap(), "x");
+    assert!(leaked == PtrUnion::<Expr>::new(Box::new(String::from("x"))).leak());
+}
+
+#[test]
+fn test_raw_tagged() {
+    use core::ffi::c_void;
+
+    type Payload = TypeList![Box<u64>, Box<String>];
+
+    // stands in for a C library which calls us back with our user data
+    extern "C" fn call_back(f: extern "C" fn(*mut c_void) -> u64, user_data: *mut c_void) -> u64 {
+        f(user_data)
+    }
+
+    extern "C" fn callback(user_data: *mut c_void) -> u64 {
+        let ptr = NonNull::new(user_data.cast()).unwrap();
+        let x = unsafe { PtrUnion::<Payload>::from_raw_tagged(ptr) };
+        match x.take::<Box<u64>, _>() {
+            Ok(value) => *value,
+            Err(x) => x.into_inner().len() as u64,
+        }
+    }
+
+    let x = PtrUnion::<Payload>::new(Box::new(String::from("four")));
+    let addr = x.addr();
+    assert_eq!(addr & Payload::MASK, 0);
+    assert_eq!(x.untagged_ptr().as_ptr() as usize, addr);
+
+    let raw = x.into_raw_tagged();
+    assert_eq!(raw.as_ptr() as usize, addr | 1);
+    assert_eq!(call_back(callback, raw.as_ptr().cast()), 4);
+
+    let raw = PtrUnion::<Payload>::new(Box::new(7u64)).into_raw_tagged();
+    assert_eq!(call_back(callback, raw.as_ptr().cast()), 7);
+}
@@ -0,0 +1,148 @@
+use super::*;
+
+use core::mem::ManuallyDrop;
+use core::ops::{Deref, DerefMut};
+use core::sync::atomic::{AtomicPtr, Ordering};
+
+/// A [`PtrUnion`] which is also its own spin lock, in a single word
+///
+/// The union is stored with the [`FlagBits<1>`](FlagBits) encoding, and the flag bit is
+/// used as the lock, so the targets need one more spare low bit than a [`PtrUnion`].
+/// The lock bit is private to the storage, the union goes in and out as a plain
+/// [`PtrUnion`]. [`lock`](Self::lock) spins until the lock is free and returns a guard
+/// which gives exclusive access to the union.
+///
+/// If a guard is leaked the union stays locked, and the union is leaked along with it.
+pub struct LockedPtrUnion<Tags: AlignedList> {
+    ptr: AtomicPtr<u8>,
+    _ty: PhantomData<PtrUnion<Tags>>,
+}
+
+/// Exclusive access to the union in a [`LockedPtrUnion`], which unlocks it when dropped
+pub struct LockedPtrUnionGuard<'a, Tags: AlignedList> {
+    lock: &'a LockedPtrUnion<Tags>,
+    value: ManuallyDrop<PtrUnion<Tags>>,
+}
+
+unsafe impl<Tags: AlignedList + Send> Send for LockedPtrUnion<Tags> {}
+// only one thread at a time can access the union, like a `Mutex`
+unsafe impl<Tags: AlignedList + Send> Sync for LockedPtrUnion<Tags> {}
+
+unsafe impl<Tags: AlignedList + Sync> Sync for LockedPtrUnionGuard<'_, Tags> {}
+
+impl<Tags: AlignedList> Drop for LockedPtrUnion<Tags> {
+    fn drop(&mut self) {
+        let bits = *self.ptr.get_mut();
+
+        // a leaked guard still owns the union
+        if bits.addr() & 1 == 0 {
+            drop(Self::from_bits(bits))
+        }
+    }
+}
+
+impl<Tags: AlignedList> From<PtrUnion<Tags>> for LockedPtrUnion<Tags> {
+    fn from(ptr: PtrUnion<Tags>) -> Self {
+        Self::new(ptr)
+    }
+}
+
+impl<Tags: AlignedList> LockedPtrUnion<Tags> {
+    /// Moves the tag of `ptr` above the lock bit, which is left clear
+    fn into_bits(ptr: PtrUnion<Tags>) -> *mut u8 {
+        let (ptr, tag) = ptr.into_raw().split();
+        RawPtrUnion::<Tags, FlagBits<1>>::from_raw(ptr, tag)
+            .into_tagged()
+            .as_ptr()
+    }
+
+    /// Takes ownership of the pointer stored in the unlocked `bits`
+    fn from_bits(bits: *mut u8) -> PtrUnion<Tags> {
+        // only non-null pointers from `into_bits` are ever stored
+        let ptr = unsafe { NonNull::new_unchecked(bits) };
+        let (ptr, tag) = unsafe { RawPtrUnion::<Tags, FlagBits<1>>::from_tagged(ptr) }.split();
+        // `FlagBits<1>` needed one more zero bit than `LowBits`, so the tag still fits
+        unsafe { PtrUnion::<Tags>::from_raw(RawPtrUnion::from_raw_unchecked(ptr, tag)) }
+    }
+
+    /// Creates a new unlocked `LockedPtrUnion`
+    pub fn new(ptr: PtrUnion<Tags>) -> Self {
+        Self {
+            ptr: AtomicPtr::new(Self::into_bits(ptr)),
+            _ty: PhantomData,
+        }
+    }
+
+    /// # Panics
+    ///
+    /// if the union is still locked by a leaked guard
+    pub fn into_inner(self) -> PtrUnion<Tags> {
+        let this = ManuallyDrop::new(self);
+        let bits = this.ptr.load(Ordering::Relaxed);
+
+        assert!(
+            bits.addr() & 1 == 0,
+            "the LockedPtrUnion is still locked by a leaked guard"
+        );
+
+        Self::from_bits(bits)
+    }
+
+    /// Locks the union if it isn't already locked
+    pub fn try_lock(&self) -> Option<LockedPtrUnionGuard<'_, Tags>> {
+        let bits = self.ptr.load(Ordering::Relaxed);
+
+        if bits.addr() & 1 != 0 {
+            return None;
+        }
+
+        self.ptr
+            .compare_exchange(
+                bits,
+                bits.wrapping_add(1),
+                Ordering::Acquire,
+                Ordering::Relaxed,
+            )
+            .ok()?;
+
+        Some(LockedPtrUnionGuard {
+            lock: self,
+            value: ManuallyDrop::new(Self::from_bits(bits)),
+        })
+    }
+
+    /// Spins until the union is unlocked, and then locks it
+    pub fn lock(&self) -> LockedPtrUnionGuard<'_, Tags> {
+        loop {
+            if let Some(guard) = self.try_lock() {
+                return guard;
+            }
+
+            core::hint::spin_loop()
+        }
+    }
+}
+
+impl<Tags: AlignedList> Deref for LockedPtrUnionGuard<'_, Tags> {
+    type Target = PtrUnion<Tags>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.value
+    }
+}
+
+impl<Tags: AlignedList> DerefMut for LockedPtrUnionGuard<'_, Tags> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.value
+    }
+}
+
+impl<Tags: AlignedList> Drop for LockedPtrUnionGuard<'_, Tags> {
+    fn drop(&mut self) {
+        let value = unsafe { ManuallyDrop::take(&mut self.value) };
+        // storing the value clears the lock bit
+        self.lock
+            .ptr
+            .store(LockedPtrUnion::into_bits(value), Ordering::Release)
+    }
+}
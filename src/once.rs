@@ -0,0 +1,122 @@
+use super::*;
+
+use core::sync::atomic::{AtomicPtr, Ordering};
+
+/// A [`PtrUnion`] which can be set exactly once, like a `OnceLock` in a single word
+///
+/// The union starts out empty (stored as a null pointer), and can be initialized with any
+/// variant from many threads at once, only the first value is kept. After that it can
+/// be borrowed with [`get`](Self::get), or through [`map_any`](Self::map_any).
+pub struct OncePtrUnion<Tags: PtrList> {
+    ptr: AtomicPtr<u8>,
+    _ty: PhantomData<PtrUnion<Tags>>,
+}
+
+unsafe impl<Tags: PtrList + Send> Send for OncePtrUnion<Tags> {}
+// the union may be set by one thread and dropped by another
+unsafe impl<Tags: PtrList + Send + Sync> Sync for OncePtrUnion<Tags> {}
+
+impl<Tags: PtrList> Default for OncePtrUnion<Tags> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<Tags: PtrList> Drop for OncePtrUnion<Tags> {
+    fn drop(&mut self) {
+        drop(self.take())
+    }
+}
+
+impl<Tags: PtrList> From<PtrUnion<Tags>> for OncePtrUnion<Tags> {
+    fn from(ptr: PtrUnion<Tags>) -> Self {
+        Self {
+            ptr: AtomicPtr::new(Self::into_bits(ptr)),
+            _ty: PhantomData,
+        }
+    }
+}
+
+impl<Tags: PtrList> OncePtrUnion<Tags> {
+    fn into_bits(ptr: PtrUnion<Tags>) -> *mut u8 {
+        ptr.into_raw().into_tagged().as_ptr()
+    }
+
+    /// Takes ownership of the pointer stored in the non-null `bits`
+    fn from_bits(bits: NonNull<u8>) -> PtrUnion<Tags> {
+        unsafe { PtrUnion::<Tags>::from_raw(RawPtrUnion::from_tagged(bits)) }
+    }
+
+    /// Creates an empty `OncePtrUnion`
+    pub const fn new() -> Self {
+        Self {
+            ptr: AtomicPtr::new(core::ptr::null_mut()),
+            _ty: PhantomData,
+        }
+    }
+
+    /// Borrows the union if it was initialized
+    pub fn get(&self) -> Option<&PtrUnion<Tags>> {
+        let bits = self.ptr.load(Ordering::Acquire);
+
+        if bits.is_null() {
+            None
+        } else {
+            // SAFETY: once the union is set it is never changed until we have a unique
+            // reference, and `PtrUnion` has the same layout as the pointer
+            Some(unsafe { &*self.ptr.as_ptr().cast::<PtrUnion<Tags>>() })
+        }
+    }
+
+    /// Calls the mapper `f` with a reference to the target of the active variant,
+    /// if the union was initialized
+    pub fn map_any<F: MapperOutput>(&self, f: F) -> Option<F::Output>
+    where
+        Tags: Map<F>,
+    {
+        self.get().map(|ptr| ptr.map_any(f))
+    }
+
+    /// Initializes the union with `ptr`, or gives it back if it was already initialized
+    pub fn set(&self, ptr: PtrUnion<Tags>) -> Result<(), PtrUnion<Tags>> {
+        let bits = Self::into_bits(ptr);
+
+        match self.ptr.compare_exchange(
+            core::ptr::null_mut(),
+            bits,
+            Ordering::AcqRel,
+            Ordering::Acquire,
+        ) {
+            Ok(_) => Ok(()),
+            Err(_) => Err(Self::from_bits(unsafe { NonNull::new_unchecked(bits) })),
+        }
+    }
+
+    /// Borrows the union, initializing it with `f` if it is empty
+    ///
+    /// If several threads initialize the union at the same time, then `f` may be called
+    /// by each of them, and every value except the one which is stored is dropped.
+    pub fn get_or_init(&self, f: impl FnOnce() -> PtrUnion<Tags>) -> &PtrUnion<Tags> {
+        if let Some(ptr) = self.get() {
+            return ptr;
+        }
+
+        // if another thread got there first then its value is kept and ours is dropped
+        let _ = self.set(f());
+
+        match self.get() {
+            Some(ptr) => ptr,
+            None => unsafe { core::hint::unreachable_unchecked() },
+        }
+    }
+
+    /// Takes the union out, leaving it empty
+    pub fn take(&mut self) -> Option<PtrUnion<Tags>> {
+        let bits = core::mem::replace(self.ptr.get_mut(), core::ptr::null_mut());
+        NonNull::new(bits).map(Self::from_bits)
+    }
+
+    pub fn into_inner(mut self) -> Option<PtrUnion<Tags>> {
+        self.take()
+    }
+}
@@ -0,0 +1,155 @@
+use super::*;
+
+use core::mem::ManuallyDrop;
+
+/// The ownership policy of a [`PtrUnion`], which decides how the tagged pointer is stored,
+/// what happens when the union is dropped and which pointer types it can hold
+///
+/// # Safety
+///
+/// `as_raw`, `as_raw_mut` and `into_raw` must return the same raw union that was passed to `from_raw`
+pub unsafe trait Ownership {
+    type Raw<Tags: PtrList, E: TagEncoding<Tags>>;
+
+    /// # Safety
+    ///
+    /// The raw union must satisfy the requirements of [`RawPtrUnion::map_any`], and
+    /// ownership of the active pointer is transferred to the returned value
+    unsafe fn from_raw<Tags: PtrList, E: TagEncoding<Tags>>(raw: E::Raw) -> Self::Raw<Tags, E>;
+
+    fn as_raw<Tags: PtrList, E: TagEncoding<Tags>>(raw: &Self::Raw<Tags, E>) -> &E::Raw;
+
+    fn as_raw_mut<Tags: PtrList, E: TagEncoding<Tags>>(raw: &mut Self::Raw<Tags, E>)
+        -> &mut E::Raw;
+
+    /// Releases ownership of the active pointer without dropping it
+    fn into_raw<Tags: PtrList, E: TagEncoding<Tags>>(raw: Self::Raw<Tags, E>) -> E::Raw;
+}
+
+/// The ownership policy can store pointers of type `P`
+///
+/// # Safety
+///
+/// Every way that the policy duplicates or drops the pointers must be valid for `P`
+pub unsafe trait Holds<P: ErasablePtr>: Ownership {}
+
+/// The ownership policy can hand out copies of a `P` that it holds from a shared reference
+///
+/// # Safety
+///
+/// `clone_from_raw` must return a `P` that can be used independently of the union
+pub unsafe trait Clones<P: ErasablePtr>: Holds<P> {
+    /// # Safety
+    ///
+    /// `ptr` must have come from `P::into_raw`, and must still be owned by a union with this policy
+    unsafe fn clone_from_raw(ptr: NonNull<()>) -> P;
+}
+
+/// Storage for a tagged pointer which drops the active pointer when it is dropped
+#[repr(transparent)]
+pub struct OwnedRaw<Tags: PtrList, E: TagEncoding<Tags> = LowBits> {
+    raw: E::Raw,
+    _ty: PhantomData<Tags>,
+}
+
+impl<Tags: PtrList, E: TagEncoding<Tags>> Drop for OwnedRaw<Tags, E> {
+    fn drop(&mut self) {
+        unsafe { E::drop_in_place(self.raw) }
+    }
+}
+
+/// Owns the active pointer and drops it with the union, the default policy of [`PtrUnion`]
+pub enum Owned {}
+
+unsafe impl Ownership for Owned {
+    type Raw<Tags: PtrList, E: TagEncoding<Tags>> = OwnedRaw<Tags, E>;
+
+    unsafe fn from_raw<Tags: PtrList, E: TagEncoding<Tags>>(raw: E::Raw) -> Self::Raw<Tags, E> {
+        OwnedRaw {
+            raw,
+            _ty: PhantomData,
+        }
+    }
+
+    fn as_raw<Tags: PtrList, E: TagEncoding<Tags>>(raw: &Self::Raw<Tags, E>) -> &E::Raw {
+        &raw.raw
+    }
+
+    fn as_raw_mut<Tags: PtrList, E: TagEncoding<Tags>>(
+        raw: &mut Self::Raw<Tags, E>,
+    ) -> &mut E::Raw {
+        &mut raw.raw
+    }
+
+    fn into_raw<Tags: PtrList, E: TagEncoding<Tags>>(raw: Self::Raw<Tags, E>) -> E::Raw {
+        ManuallyDrop::new(raw).raw
+    }
+}
+
+unsafe impl<P: ErasablePtr> Holds<P> for Owned {}
+
+/// Owns the active pointer like [`Owned`], but only holds `Clone` pointers so that
+/// the union can be cloned
+pub enum Shared {}
+
+unsafe impl Ownership for Shared {
+    type Raw<Tags: PtrList, E: TagEncoding<Tags>> = OwnedRaw<Tags, E>;
+
+    unsafe fn from_raw<Tags: PtrList, E: TagEncoding<Tags>>(raw: E::Raw) -> Self::Raw<Tags, E> {
+        Owned::from_raw(raw)
+    }
+
+    fn as_raw<Tags: PtrList, E: TagEncoding<Tags>>(raw: &Self::Raw<Tags, E>) -> &E::Raw {
+        Owned::as_raw(raw)
+    }
+
+    fn as_raw_mut<Tags: PtrList, E: TagEncoding<Tags>>(
+        raw: &mut Self::Raw<Tags, E>,
+    ) -> &mut E::Raw {
+        Owned::as_raw_mut(raw)
+    }
+
+    fn into_raw<Tags: PtrList, E: TagEncoding<Tags>>(raw: Self::Raw<Tags, E>) -> E::Raw {
+        Owned::into_raw(raw)
+    }
+}
+
+unsafe impl<P: ErasablePtr + Clone> Holds<P> for Shared {}
+unsafe impl<P: ErasablePtr + Clone> Clones<P> for Shared {
+    unsafe fn clone_from_raw(ptr: NonNull<()>) -> P {
+        let ptr = ManuallyDrop::new(P::from_raw(ptr));
+        P::clone(&ptr)
+    }
+}
+
+/// Only holds `Copy` pointers, so the union itself is `Copy`, the policy of [`CopyPtrUnion`]
+pub enum Copied {}
+
+unsafe impl Ownership for Copied {
+    type Raw<Tags: PtrList, E: TagEncoding<Tags>> = E::Raw;
+
+    unsafe fn from_raw<Tags: PtrList, E: TagEncoding<Tags>>(raw: E::Raw) -> Self::Raw<Tags, E> {
+        raw
+    }
+
+    fn as_raw<Tags: PtrList, E: TagEncoding<Tags>>(raw: &Self::Raw<Tags, E>) -> &E::Raw {
+        raw
+    }
+
+    fn as_raw_mut<Tags: PtrList, E: TagEncoding<Tags>>(
+        raw: &mut Self::Raw<Tags, E>,
+    ) -> &mut E::Raw {
+        raw
+    }
+
+    fn into_raw<Tags: PtrList, E: TagEncoding<Tags>>(raw: Self::Raw<Tags, E>) -> E::Raw {
+        raw
+    }
+}
+
+unsafe impl<P: ErasablePtr + Copy> Holds<P> for Copied {}
+unsafe impl<P: ErasablePtr + Copy> Clones<P> for Copied {
+    unsafe fn clone_from_raw(ptr: NonNull<()>) -> P {
+        P::from_raw(ptr)
+    }
+}
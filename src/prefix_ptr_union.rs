@@ -0,0 +1,190 @@
+use super::*;
+
+/// Stores the tag in the low bits of the pointer, giving every pointer type a tag as long
+/// as the alignment of its target allows
+///
+/// The tags form a prefix code, read from the lowest bit up, so no tag is the low bits of
+/// another tag. The pointer type at index `i` gets a tag of `Tags::ALIGN_BITS[i]` bits, so
+/// highly aligned pointers don't have to share a tag width with the least aligned one. For
+/// example one `&u16` (1 bit) and four `Box<u64>` (3 bits each) fit in a single word, while
+/// [`LowBits`] would need the `&u16` to be aligned to 8.
+///
+/// A prefix code only exists if the sum of `2^-ALIGN_BITS[i]` over the list is at most 1,
+/// using a list without one fails to compile when the union is constructed.
+pub enum Prefix {}
+
+impl sealed::SealedEncoding for Prefix {}
+
+struct PrefixCode {
+    masks: [usize; 256],
+    codes: [usize; 256],
+}
+
+const fn prefix_code(bits: &[u8; 256], len: u8) -> PrefixCode {
+    let len = len as usize;
+    let mut masks = [0; 256];
+    let mut codes = [0; 256];
+    let mut assigned = [false; 256];
+
+    // a canonical prefix code, assigned from the shortest tag to the longest
+    let mut code = 0usize;
+    let mut code_len = 0;
+    let mut n = 0;
+
+    while n < len {
+        let mut best = len;
+        let mut i = 0;
+        while i < len {
+            if !assigned[i] && (best == len || bits[i] < bits[best]) {
+                best = i;
+            }
+            i += 1;
+        }
+
+        assigned[best] = true;
+        let tag_len = bits[best] as u32;
+        code <<= tag_len - code_len;
+
+        assert!(
+            code >> tag_len == 0,
+            "the pointers in this list aren't aligned enough to give every pointer type a tag"
+        );
+
+        if tag_len != 0 {
+            masks[best] = usize::MAX >> (usize::BITS - tag_len);
+            // the canonical code has the shared prefixes in the high bits, but the tag is
+            // read from the lowest bit up
+            codes[best] = code.reverse_bits() >> (usize::BITS - tag_len);
+        }
+
+        code += 1;
+        code_len = tag_len;
+        n += 1;
+    }
+
+    PrefixCode { masks, codes }
+}
+
+/// A pointer tagged with a [`Prefix`] tag of the index of its pointer type in `Tags`
+///
+/// This is the [`TagEncoding::Raw`] of [`Prefix`], and like [`RawPtrUnion`] it doesn't own
+/// the pointer. This has the same layout as a `NonNull<u8>`.
+#[repr(transparent)]
+pub struct RawPrefixPtrUnion<Tags> {
+    ptr: NonNull<u8>,
+    _ty: PhantomData<Tags>,
+}
+
+unsafe impl<Tags: Send> Send for RawPrefixPtrUnion<Tags> {}
+unsafe impl<Tags: Sync> Sync for RawPrefixPtrUnion<Tags> {}
+
+impl<Tags> Copy for RawPrefixPtrUnion<Tags> {}
+impl<Tags> Clone for RawPrefixPtrUnion<Tags> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<Tags: AlignedList> RawPrefixPtrUnion<Tags> {
+    const CODE: &'static PrefixCode = &prefix_code(&Tags::ALIGN_BITS, Tags::LEN);
+
+    /// Splits this union into the untagged pointer and the tag, the tag is always less
+    /// than `Tags::LEN`
+    pub const fn split(&self) -> (NonNull<()>, u8) {
+        let addr = addr(self.ptr).get();
+        let mut tag = 0;
+
+        while tag < Tags::LEN {
+            let code = Self::CODE.codes[tag as usize];
+
+            if addr & Self::CODE.masks[tag as usize] == code {
+                // we must use wrapping_sub here so that we preserve the provenance of the pointer
+                let ptr = self.ptr.as_ptr().wrapping_sub(code);
+                return (unsafe { NonNull::new_unchecked(ptr.cast()) }, tag);
+            }
+
+            tag += 1;
+        }
+
+        unsafe { core::hint::unreachable_unchecked() }
+    }
+
+    /// Tags the pointer with `tag`, or returns an error if the pointer isn't aligned
+    /// to its target
+    ///
+    /// # Panics
+    ///
+    /// if `tag >= Tags::LEN`
+    pub const fn try_from_raw(ptr: NonNull<()>, tag: u8) -> Result<Self, InvalidAlignment> {
+        assert!(tag < Tags::LEN, "invalid tag");
+
+        if addr(ptr).get() & Self::CODE.masks[tag as usize] == 0 {
+            Ok(unsafe { Self::from_raw_unchecked(ptr, tag) })
+        } else {
+            Err(InvalidAlignment)
+        }
+    }
+
+    /// Tags the pointer with `tag`
+    ///
+    /// # Panics
+    ///
+    /// if `tag >= Tags::LEN`, or if the pointer isn't aligned to its target
+    pub const fn from_raw(ptr: NonNull<()>, tag: u8) -> Self {
+        match Self::try_from_raw(ptr, tag) {
+            Ok(raw) => raw,
+            Err(InvalidAlignment) => panic!("Invalid pointer alignment for this PtrUnion"),
+        }
+    }
+
+    /// Tags the pointer with `tag` without checking the tag or the alignment
+    ///
+    /// # Safety
+    ///
+    /// `tag` must be less than `Tags::LEN`, and `ptr` must be aligned to the target of the
+    /// pointer type at index `tag`
+    pub const unsafe fn from_raw_unchecked(ptr: NonNull<()>, tag: u8) -> Self {
+        debug_assert!(tag < Tags::LEN);
+        debug_assert!(addr(ptr).get() & Self::CODE.masks[tag as usize] == 0);
+
+        let code = Self::CODE.codes[tag as usize];
+
+        Self {
+            // we must use wrapping_add here so that we preserve the provenance of the pointer
+            ptr: unsafe { NonNull::new_unchecked(ptr.as_ptr().cast::<u8>().wrapping_add(code)) },
+            _ty: PhantomData,
+        }
+    }
+}
+
+unsafe impl<Tags: AlignedList> TagEncoding<Tags> for Prefix {
+    type Raw = RawPrefixPtrUnion<Tags>;
+
+    fn split(raw: &Self::Raw) -> (NonNull<()>, u8) {
+        raw.split()
+    }
+
+    fn try_from_raw(ptr: NonNull<()>, tag: u8) -> Result<Self::Raw, InvalidAlignment> {
+        RawPrefixPtrUnion::try_from_raw(ptr, tag)
+    }
+
+    fn from_raw(ptr: NonNull<()>, tag: u8) -> Self::Raw {
+        RawPrefixPtrUnion::from_raw(ptr, tag)
+    }
+
+    unsafe fn from_raw_unchecked(ptr: NonNull<()>, tag: u8) -> Self::Raw {
+        unsafe { RawPrefixPtrUnion::from_raw_unchecked(ptr, tag) }
+    }
+
+    fn ptr_eq(this: &Self::Raw, other: &Self::Raw) -> bool {
+        this.ptr == other.ptr
+    }
+
+    fn ptr_hash<S: core::hash::Hasher>(this: &Self::Raw, state: &mut S) {
+        core::ptr::hash(this.ptr.as_ptr(), state)
+    }
+}
+
+/// A [`PtrUnion`] which gives every pointer type a tag as long as the alignment of its
+/// target allows, see [`Prefix`]
+pub type PrefixPtrUnion<Tags, O = Owned> = PtrUnion<Tags, O, Prefix>;
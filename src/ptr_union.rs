@@ -1,198 +1,407 @@
 use super::*;
 
+/// A union of the pointer types in `Tags`, stored in a single pointer
+///
+/// The ownership policy `O` decides what happens to the active pointer when the union is
+/// dropped, copied or cloned, see [`Owned`], [`Shared`] and [`Copied`].
+/// The [`TagEncoding`] `E` decides where the tag is stored in the pointer.
 #[repr(transparent)]
-pub struct PtrUnion<Tags: PtrList> {
-    raw: RawPtrUnion<Tags>,
-    _ty: PhantomData<Tags>,
+pub struct PtrUnion<Tags: PtrList, O: Ownership = Owned, E: TagEncoding<Tags> = LowBits> {
+    raw: O::Raw<Tags, E>,
 }
 
-impl<Tags: PtrList> Drop for PtrUnion<Tags> {
-    fn drop(&mut self) {
-        let (ptr, tag) = self.raw.split();
-        unsafe { <Tags as PtrList>::drop_at(ptr, tag) }
+/// A [`PtrUnion`] of `Copy` pointers, which is itself `Copy`
+pub type CopyPtrUnion<Tags> = PtrUnion<Tags, Copied>;
+
+/// A [`PtrUnion`] of `Clone` pointers, which is itself `Clone`
+pub type SharedPtrUnion<Tags> = PtrUnion<Tags, Shared>;
+
+cfg_high_bits! {
+    /// A [`PtrUnion`] which stores the tag in the upper 16 bits of the pointer,
+    /// so it can hold up to 255 variants regardless of their alignment
+    pub type HighBitsPtrUnion<Tags, O = Owned> = PtrUnion<Tags, O, HighBits>;
+}
+
+/// A [`PtrUnion`] which stores `FLAG_BITS` user flag bits alongside the tag, see [`FlagBits`]
+pub type PtrUnionWithFlags<Tags, const FLAG_BITS: usize, O = Owned> =
+    PtrUnion<Tags, O, FlagBits<FLAG_BITS>>;
+
+impl<Tags: PtrList, E: TagEncoding<Tags>> Copy for PtrUnion<Tags, Copied, E> {}
+impl<Tags: PtrList, E: TagEncoding<Tags>> Clone for PtrUnion<Tags, Copied, E> {
+    fn clone(&self) -> Self {
+        *self
     }
 }
 
-impl<Tags: PtrList> PtrUnion<Tags> {
-    pub fn new<P: ErasablePtr, N: Peano>(ptr: P) -> Self
+impl<Tags: PtrList + MapClone, E: TagEncoding<Tags>> Clone for PtrUnion<Tags, Shared, E> {
+    fn clone(&self) -> Self {
+        let (ptr, tag) = self.split();
+        let ptr = unsafe { Tags::clone_at(ptr, tag) };
+        // a deep clone is a new allocation, which isn't guaranteed to leave room for the tag
+        let raw = E::try_from_raw(ptr, tag).unwrap_or_else(|_| {
+            unsafe { Tags::drop_at(ptr, tag) };
+            panic!("the cloned pointer doesn't have enough free bits to store the tag")
+        });
+        unsafe { Self::from_raw(E::with_flags(raw, E::flags(self.raw()))) }
+    }
+}
+
+impl<Tags: PtrList, O: Ownership, E: TagEncoding<Tags>> PtrUnion<Tags, O, E> {
+    pub(crate) unsafe fn from_raw<NewTags: PtrList>(
+        raw: <E as TagEncoding<NewTags>>::Raw,
+    ) -> PtrUnion<NewTags, O, E>
     where
-        Tags: Access<P, N>,
+        E: TagEncoding<NewTags>,
     {
-        Self {
-            raw: RawPtrUnion::from_raw(P::into_raw(ptr), N::VALUE),
-            _ty: PhantomData,
+        PtrUnion {
+            raw: unsafe { O::from_raw(raw) },
         }
     }
 
+    fn raw(&self) -> &E::Raw {
+        O::as_raw(&self.raw)
+    }
+
+    pub(crate) fn into_raw(self) -> E::Raw {
+        O::into_raw(self.raw)
+    }
+
+    pub(crate) fn split(&self) -> (NonNull<()>, u8) {
+        E::split(self.raw())
+    }
+
+    pub fn new<P: DerefRaw, N: Peano>(ptr: P) -> Self
+    where
+        Tags: Access<P, N>,
+        O: Holds<P>,
+    {
+        unsafe { Self::from_raw(E::from_raw(P::into_union_raw(ptr), N::VALUE)) }
+    }
+
     pub fn set<T, N>(&mut self, value: T)
     where
-        T: ErasablePtr,
+        T: DerefRaw,
         N: Peano,
         Tags: Access<T, N>,
+        O: Holds<T>,
     {
         *self = Self::new(value);
     }
 
     pub fn tag(&self) -> usize {
-        self.raw.split().1 as usize
+        self.split().1 as usize
     }
 
+    /// The active pointer without the tag, as returned by [`DerefRaw::into_union_raw`]
+    ///
+    /// This is the address of the target only for variants which point to one. A dangling
+    /// pointer to a zero-sized target is replaced by a sentinel address, and variants which
+    /// don't point to anything, like [`Inline`] and the markers from
+    /// [`sentinel!`](crate::sentinel), return the value they encode in the address.
+    pub fn untagged_ptr(&self) -> NonNull<()> {
+        self.split().0
+    }
+
+    /// The address of [`untagged_ptr`](Self::untagged_ptr), so for [`Inline`] variants this
+    /// is the encoded integer rather than a memory address
+    pub fn addr(&self) -> usize {
+        addr(self.untagged_ptr()).get()
+    }
+
+    /// Checks if the two unions have the same pointer and tag, and the same flags if the
+    /// encoding has any
     pub fn ptr_eq(this: &Self, other: &Self) -> bool {
-        RawPtrUnion::ptr_eq(&this.raw, &other.raw)
+        E::ptr_eq(this.raw(), other.raw())
     }
 
+    /// Hashes the pointer, tag and flags, consistent with [`ptr_eq`](Self::ptr_eq)
     pub fn ptr_hash<S: core::hash::Hasher>(this: &Self, state: &mut S) {
-        RawPtrUnion::ptr_hash(&this.raw, state)
+        E::ptr_hash(this.raw(), state)
     }
 
     pub fn map_any<F: MapperOutput>(&self, f: F) -> F::Output
     where
         Tags: Map<F>,
     {
-        unsafe { self.raw.map_any(f) }
+        unsafe { E::map_any(self.raw(), f) }
     }
 
     pub fn is<P: ErasablePtr, N: Peano>(&self) -> bool
     where
         Tags: Access<P, N>,
     {
-        self.raw.split().1 == N::VALUE
+        self.split().1 == N::VALUE
+    }
+
+    /// Borrows the target of the active pointer if it is a `P`
+    pub fn get<P, N: Peano>(&self) -> Option<&P::Target>
+    where
+        P: ErasablePtr + core::ops::Deref,
+        P::Target: Erasable,
+        Tags: Access<P, N>,
+    {
+        if self.is::<P, N>() {
+            // the blanket `DerefRaw` impl lends out the target in place, and the union owns or
+            // borrows the target for as long as it is borrowed
+            let target = unsafe {
+                <P as DerefRaw>::with_target(self.split().0, |target| target as *const _)
+            };
+            Some(unsafe { &*target })
+        } else {
+            None
+        }
     }
 
+    /// Returns a copy of the active pointer if it is a `P`
+    ///
+    /// Only policies which can duplicate a `P` support this, take the pointer out of an
+    /// [`Owned`] union with [`take`](Self::take) or [`unpack`](Self::unpack) instead
     pub fn try_cast<P: ErasablePtr, N: Peano>(&self) -> Option<P>
     where
         Tags: Access<P, N>,
+        O: Clones<P>,
     {
         if self.is::<P, N>() {
-            Some(unsafe { P::from_raw(self.raw.split().0) })
+            Some(unsafe { O::clone_from_raw(self.split().0) })
         } else {
             None
         }
     }
 
-    pub fn unpack(&self) -> Tags {
-        let (ptr, tag) = self.raw.split();
-        unsafe { Tags::into_inner(ptr, tag) }
+    /// Converts the union into the enum of its pointer list, moving the active pointer out
+    pub fn unpack(self) -> Tags {
+        unsafe { E::unpack(self.into_raw()) }
     }
 
-    pub fn try_to_superset<NewTags, Ns>(self) -> Result<PtrUnion<NewTags>, InvalidAlignment>
+    pub fn try_to_superset<NewTags, Ns>(self) -> Result<PtrUnion<NewTags, O, E>, InvalidAlignment>
     where
         Tags: SubsetOf<NewTags, Ns>,
         NewTags: PtrList,
+        E: TagEncoding<NewTags>,
     {
-        self.raw.try_to_superset().map(|raw| PtrUnion {
-            raw,
-            _ty: PhantomData,
-        })
+        let raw = <E as TagEncoding<Tags>>::try_to_superset(*self.raw())?;
+        let _ = self.into_raw();
+        Ok(unsafe { Self::from_raw(raw) })
     }
 
-    pub fn to_superset<NewTags, Ns>(self) -> PtrUnion<NewTags>
+    pub fn to_superset<NewTags, Ns>(self) -> PtrUnion<NewTags, O, E>
     where
         Tags: SubsetOf<NewTags, Ns>,
         NewTags: PtrList,
+        E: TagEncoding<NewTags>,
     {
-        let raw = self.raw.to_superset();
-        PtrUnion {
-            raw,
-            _ty: PhantomData,
-        }
+        let raw = <E as TagEncoding<Tags>>::to_superset(self.into_raw());
+        unsafe { Self::from_raw(raw) }
     }
 
-    pub fn reorganize<NewTags, Ns>(self) -> PtrUnion<NewTags>
+    pub fn reorganize<NewTags, Ns>(self) -> PtrUnion<NewTags, O, E>
     where
         Tags: SubsetOf<NewTags, Ns, Remaining = Nil>,
         NewTags: PtrList,
+        E: TagEncoding<NewTags>,
     {
-        let raw = self.raw.reorganize();
-        PtrUnion {
-            raw,
-            _ty: PhantomData,
-        }
+        let raw = <E as TagEncoding<Tags>>::reorganize(self.into_raw());
+        unsafe { Self::from_raw(raw) }
     }
 
     pub fn try_to_subset<NewTags, Ns>(
         self,
-    ) -> Result<PtrUnion<NewTags>, PtrUnion<NewTags::Remaining>>
+    ) -> Result<PtrUnion<NewTags, O, E>, PtrUnion<NewTags::Remaining, O, E>>
     where
         NewTags: SubsetOf<Tags, Ns>,
+        E: TagEncoding<NewTags> + TagEncoding<NewTags::Remaining>,
     {
-        match self.raw.try_to_subset() {
-            Ok(raw) => Ok(PtrUnion {
-                raw,
-                _ty: PhantomData,
-            }),
-            Err(raw) => Err(PtrUnion {
-                raw,
-                _ty: PhantomData,
-            }),
+        match <E as TagEncoding<Tags>>::try_to_subset::<NewTags, Ns>(self.into_raw()) {
+            Ok(raw) => Ok(unsafe { Self::from_raw(raw) }),
+            Err(raw) => Err(unsafe { Self::from_raw(raw) }),
         }
     }
 
-    pub fn take<T: ErasablePtr, N: Peano>(self) -> Result<T, PtrUnion<Tags::Remaining>>
+    pub fn take<T: ErasablePtr, N: Peano>(self) -> Result<T, PtrUnion<Tags::Remaining, O, E>>
     where
         Tags: Access<T, N>,
+        E: TagEncoding<TypeList![T]> + TagEncoding<Tags::Remaining>,
     {
         self.try_to_subset::<TypeList![T], TypeList![N]>()
             .map(PtrUnion::into_inner)
     }
 }
 
-impl<T: ErasablePtr> PtrUnion<TypeList![T]> {
+impl<Tags: PtrList, O: Ownership, E: TagEncoding<Tags>> PtrUnion<Tags, O, E> {
+    /// Re-tags this union for `NewTags`, which has the same targets at every index
+    ///
+    /// # Safety
+    ///
+    /// The active pointer must be valid for the pointer type at the same index in `NewTags`
+    unsafe fn retype<NewTags: PtrList>(&self) -> <E as TagEncoding<NewTags>>::Raw
+    where
+        E: TagEncoding<NewTags>,
+    {
+        let (ptr, tag) = self.split();
+        // the targets are the same, so the pointer is aligned enough for the same tag
+        let raw = unsafe { <E as TagEncoding<NewTags>>::from_raw_unchecked(ptr, tag) };
+        <E as TagEncoding<NewTags>>::with_flags(raw, <E as TagEncoding<Tags>>::flags(self.raw()))
+    }
+
+    /// Reborrows a union of references for a shorter lifetime, so that a union of `&mut`
+    /// references can be passed on without giving it up
+    pub fn reborrow<'a>(&'a mut self) -> PtrUnion<Tags::Reborrowed, O, E>
+    where
+        Tags: Reborrow<'a>,
+        E: TagEncoding<Tags::Reborrowed>,
+    {
+        // the new union borrows `self` mutably, so the references stay unique
+        unsafe { Self::from_raw(self.retype::<Tags::Reborrowed>()) }
+    }
+
+    /// Borrows a union of references as a union of shared references
+    pub fn as_refs<'a>(&'a self) -> PtrUnion<Tags::Shared, Copied, E>
+    where
+        Tags: Reborrow<'a>,
+        E: TagEncoding<Tags::Shared>,
+    {
+        unsafe { PtrUnion::<Tags::Shared, Copied, E>::from_raw(self.retype::<Tags::Shared>()) }
+    }
+
+    /// Borrows the targets of this union as a union of references with the same tag,
+    /// which is cheap to copy and compares the targets like this union
+    pub fn as_ref_union<'a>(&'a self) -> PtrUnion<Tags::Refs, Copied, E>
+    where
+        Tags: AsRefList<'a>,
+        E: TagEncoding<Tags::Refs>,
+    {
+        // the raw pointer of every pointer in the list is a pointer to its target
+        unsafe { PtrUnion::<Tags::Refs, Copied, E>::from_raw(self.retype::<Tags::Refs>()) }
+    }
+
+    /// Leaks every box in this union, like `Box::leak`, and returns a union of `'static`
+    /// references with the same tag
+    pub fn leak(self) -> PtrUnion<Tags::Leaked, Copied, E>
+    where
+        Tags: LeakList,
+        E: TagEncoding<Tags::Leaked>,
+    {
+        let raw = unsafe { self.retype::<Tags::Leaked>() };
+        let _ = self.into_raw();
+        unsafe { PtrUnion::<Tags::Leaked, Copied, E>::from_raw(raw) }
+    }
+}
+
+impl<Tags, O, E> PtrUnion<Tags, O, E>
+where
+    Tags: PtrList,
+    O: Ownership,
+    E: TagEncoding<Tags, Raw = RawPtrUnion<Tags, E>> + PackedEncoding<Tags>,
+{
+    /// Converts this union into the tagged pointer it is stored as, for example to pass it
+    /// through C as an opaque `void*`, use [`from_raw_tagged`](Self::from_raw_tagged) to get
+    /// it back
+    ///
+    /// Ownership of the active pointer is transferred to the tagged pointer, so it is leaked
+    /// unless it is converted back.
+    pub fn into_raw_tagged(self) -> NonNull<u8> {
+        self.into_raw().into_tagged()
+    }
+
+    /// Converts a tagged pointer from [`into_raw_tagged`](Self::into_raw_tagged) back into a
+    /// union, taking ownership of the active pointer
+    ///
+    /// # Safety
+    ///
+    /// `ptr` must have come from `into_raw_tagged` of a union with the same `Tags`, `O` and
+    /// `E`, and it must not be converted back more than once (unless `O` is [`Copied`])
+    pub unsafe fn from_raw_tagged(ptr: NonNull<u8>) -> Self {
+        unsafe { Self::from_raw(RawPtrUnion::from_tagged(ptr)) }
+    }
+}
+
+impl<Tags: AlignedList, O: Ownership, const N: usize> PtrUnion<Tags, O, FlagBits<N>> {
+    /// The user flag bits, which are zero for a new union
+    ///
+    /// The flags are ignored by `==`, `Ord` and `Hash`, which only look at the tag and the
+    /// target, but [`ptr_eq`](Self::ptr_eq) and [`ptr_hash`](Self::ptr_hash) include them.
+    pub fn flags(&self) -> usize {
+        self.raw().flags()
+    }
+
+    /// Replaces the user flag bits
+    ///
+    /// # Panics
+    ///
+    /// if `flags` doesn't fit in `N` bits
+    pub fn set_flags(&mut self, flags: usize) {
+        let raw = O::as_raw_mut(&mut self.raw);
+        *raw = raw.with_flags(flags);
+    }
+}
+
+impl<T: ErasablePtr, O: Ownership, E: TagEncoding<TypeList![T]>> PtrUnion<TypeList![T], O, E> {
     pub fn into_inner(self) -> T {
-        unsafe { self.raw.into_inner() }
+        let (ptr, _) = E::split(&self.into_raw());
+        unsafe { T::from_raw(ptr) }
     }
 }
 
-impl PtrUnion<TypeList![]> {
+impl<O: Ownership, E: TagEncoding<TypeList![]>> PtrUnion<TypeList![], O, E> {
     pub fn unreachable(self) -> ! {
-        self.raw.unreachable()
+        unsafe { core::hint::unreachable_unchecked() }
     }
 }
 
-impl<Tags> Eq for PtrUnion<Tags> where Tags: PtrList + Map<EqAny> + Map<PartialEqAny> {}
-impl<Tags> PartialEq for PtrUnion<Tags>
+impl<Tags, O: Ownership, E: TagEncoding<Tags>> Eq for PtrUnion<Tags, O, E> where
+    Tags: PtrList + MapPair<PartialEqAny> + AllTargets<pred::Eq>
+{
+}
+impl<Tags, O: Ownership, E: TagEncoding<Tags>> PartialEq for PtrUnion<Tags, O, E>
 where
-    Tags: PtrList + Map<PartialEqAny>,
+    Tags: PtrList + MapPair<PartialEqAny>,
 {
     fn eq(&self, other: &Self) -> bool {
-        let (ptr, tag) = other.raw.split();
-        self.raw.split().1 == tag && self.map_any(PartialEqAny(ptr))
+        let (a, tag) = self.split();
+        let (b, other_tag) = other.split();
+        tag == other_tag && unsafe { Tags::map_pair(a, b, tag, PartialEqAny) }
     }
 }
 
-impl<Tags> PartialOrd for PtrUnion<Tags>
+impl<Tags, O: Ownership, E: TagEncoding<Tags>> PartialOrd for PtrUnion<Tags, O, E>
 where
-    Tags: PtrList + Map<PartialEqAny> + Map<PartialOrdAny>,
+    Tags: PtrList + MapPair<PartialEqAny> + MapPair<PartialOrdAny>,
 {
     fn partial_cmp(&self, other: &Self) -> Option<core::cmp::Ordering> {
-        let (ptr, tag) = other.raw.split();
+        let (a, tag) = self.split();
+        let (b, other_tag) = other.split();
 
-        match self.raw.split().1.cmp(&tag) {
+        match tag.cmp(&other_tag) {
             o @ (std::cmp::Ordering::Less | std::cmp::Ordering::Greater) => Some(o),
-            std::cmp::Ordering::Equal => self.map_any(PartialOrdAny(ptr)),
+            std::cmp::Ordering::Equal => unsafe { Tags::map_pair(a, b, tag, PartialOrdAny) },
         }
     }
 }
 
-impl<Tags> Ord for PtrUnion<Tags>
+impl<Tags, O: Ownership, E: TagEncoding<Tags>> Ord for PtrUnion<Tags, O, E>
 where
-    Tags: PtrList + Map<PartialEqAny> + Map<PartialOrdAny> + Map<EqAny> + Map<OrdAny>,
+    Tags: PtrList
+        + MapPair<PartialEqAny>
+        + MapPair<PartialOrdAny>
+        + AllTargets<pred::Eq>
+        + MapPair<OrdAny>,
 {
     fn cmp(&self, other: &Self) -> core::cmp::Ordering {
-        let (ptr, tag) = other.raw.split();
+        let (a, tag) = self.split();
+        let (b, other_tag) = other.split();
 
-        match self.raw.split().1.cmp(&tag) {
+        match tag.cmp(&other_tag) {
             o @ (std::cmp::Ordering::Less | std::cmp::Ordering::Greater) => o,
-            std::cmp::Ordering::Equal => self.map_any(OrdAny(ptr)),
+            std::cmp::Ordering::Equal => unsafe { Tags::map_pair(a, b, tag, OrdAny) },
         }
     }
 }
 
-impl<Tags> core::hash::Hash for PtrUnion<Tags>
+impl<Tags, O: Ownership, E: TagEncoding<Tags>> core::hash::Hash for PtrUnion<Tags, O, E>
 where
     Tags: PtrList + MapHash,
 {
     fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
-        unsafe { self.raw.map_hash(state) }
+        unsafe { E::map_hash(self.raw(), state) }
     }
 }
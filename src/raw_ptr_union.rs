@@ -1,28 +1,46 @@
 use super::*;
 
+/// A pointer tagged with the index of its pointer type in `Tags`, without any ownership
+///
+/// The tag is packed into the pointer as described by the [`PackedEncoding`] `E`. By default
+/// it is stored in the low bits of the pointer, so every pointer stored in a `RawPtrUnion`
+/// must be aligned to at least `Tags::MASK + 1`. This has the same layout as a `NonNull<u8>`.
+///
+/// `RawPtrUnion` never drops or clones the pointer it holds, it is the building block
+/// for containers that pick their own ownership policy, like [`PtrUnion`] (which owns the
+/// pointer and drops it) and [`CopyPtrUnion`] (which only holds `Copy` pointers).
+///
+/// It is always safe to construct a `RawPtrUnion` and to inspect its tag, since the tag is
+/// validated on construction. Any operation that dereferences the pointer or takes ownership
+/// of it is `unsafe` and requires that the pointer came from `ErasablePtr::into_raw` of the
+/// pointer type at the given tag, and that it is still valid for that pointer type.
 #[repr(transparent)]
-pub(crate) struct RawPtrUnion<Tags> {
+pub struct RawPtrUnion<Tags, E = LowBits> {
     ptr: NonNull<u8>,
-    _ty: PhantomData<Tags>,
+    _ty: PhantomData<(Tags, fn() -> E)>,
 }
 
-unsafe impl<Tags: Send> Send for RawPtrUnion<Tags> {}
-unsafe impl<Tags: Sync> Sync for RawPtrUnion<Tags> {}
+unsafe impl<Tags: Send, E> Send for RawPtrUnion<Tags, E> {}
+unsafe impl<Tags: Sync, E> Sync for RawPtrUnion<Tags, E> {}
 
-impl<Tags> Copy for RawPtrUnion<Tags> {}
-impl<Tags> Clone for RawPtrUnion<Tags> {
+impl<Tags, E> Copy for RawPtrUnion<Tags, E> {}
+impl<Tags, E> Clone for RawPtrUnion<Tags, E> {
     fn clone(&self) -> Self {
         *self
     }
 }
 
-impl<Tags: PtrList> RawPtrUnion<Tags> {
-    pub(crate) const fn split(&self) -> (NonNull<()>, u8) {
+impl<Tags: PtrList, E: PackedEncoding<Tags>> RawPtrUnion<Tags, E> {
+    /// Splits this union into the untagged pointer and the tag
+    ///
+    /// The tag is always less than `Tags::LEN`, and the pointer is exactly the pointer that
+    /// was passed to [`from_raw`](Self::from_raw) (with the same provenance)
+    pub const fn split(&self) -> (NonNull<()>, u8) {
         let addr = addr(self.ptr).get();
-        let tag = addr & Tags::MASK;
+        let tag = addr & E::TAG_MASK;
         // we must use wrapping_sub here so that we preserve the provenance of the pointer
-        let ptr = self.ptr.as_ptr().wrapping_sub(tag);
-        let tag = tag as u8;
+        let ptr = self.ptr.as_ptr().wrapping_sub(tag | addr & E::FLAG_MASK);
+        let tag = (tag >> E::TAG_SHIFT) as u8;
 
         if tag >= Tags::LEN {
             unsafe { core::hint::unreachable_unchecked() }
@@ -31,14 +49,70 @@ impl<Tags: PtrList> RawPtrUnion<Tags> {
         (unsafe { NonNull::new_unchecked(ptr.cast()) }, tag)
     }
 
+    /// The flag bits stored next to the tag, always zero unless `E` has a `FLAG_MASK`
+    pub const fn flags(&self) -> usize {
+        addr(self.ptr).get() & E::FLAG_MASK
+    }
+
+    /// Replaces the flag bits stored next to the tag
+    ///
+    /// # Panics
+    ///
+    /// if `flags` has any bits set outside of `E::FLAG_MASK`
+    pub const fn with_flags(self, flags: usize) -> Self {
+        assert!(
+            flags & !E::FLAG_MASK == 0,
+            "the flags don't fit in the spare bits of this PtrUnion"
+        );
+
+        // we must use wrapping_sub/wrapping_add here so that we preserve the provenance of the pointer
+        let ptr = self
+            .ptr
+            .as_ptr()
+            .wrapping_sub(self.flags())
+            .wrapping_add(flags);
+
+        Self {
+            // SAFETY: the flags are only ever stored in bits which are zero in the untagged pointer
+            ptr: unsafe { NonNull::new_unchecked(ptr) },
+            _ty: PhantomData,
+        }
+    }
+
+    /// The tagged pointer, exactly as it is stored
+    pub(crate) const fn into_tagged(self) -> NonNull<u8> {
+        self.ptr
+    }
+
+    /// # Safety
+    ///
+    /// `ptr` must have come from [`into_tagged`](Self::into_tagged) of a union with the same
+    /// `Tags` and `E`
+    pub(crate) const unsafe fn from_tagged(ptr: NonNull<u8>) -> Self {
+        Self {
+            ptr,
+            _ty: PhantomData,
+        }
+    }
+
+    /// Checks if the two unions have the same pointer, tag and flags
     pub fn ptr_eq(this: &Self, other: &Self) -> bool {
         this.ptr == other.ptr
     }
 
+    /// Hashes the pointer, tag and flags, consistent with [`ptr_eq`](Self::ptr_eq)
     pub fn ptr_hash<S: core::hash::Hasher>(this: &Self, state: &mut S) {
         core::ptr::hash(this.ptr.as_ptr(), state)
     }
 
+    /// Calls the mapper `f` with a reference to the target of the active variant
+    ///
+    /// # Safety
+    ///
+    /// The pointer must have come from `ErasablePtr::into_raw` of the pointer type at the
+    /// current tag, and it must be valid to create a shared reference to its target for the
+    /// duration of this call (i.e. it hasn't been dropped or moved out of, and there
+    /// are no active mutable references to the target)
     pub unsafe fn map_any<F: MapperOutput>(self, f: F) -> F::Output
     where
         Tags: Map<F>,
@@ -47,6 +121,11 @@ impl<Tags: PtrList> RawPtrUnion<Tags> {
         Tags::map(ptr, tag, f)
     }
 
+    /// Hashes the tag and the target of the active variant
+    ///
+    /// # Safety
+    ///
+    /// The same as [`map_any`](Self::map_any)
     pub unsafe fn map_hash<S: core::hash::Hasher>(self, state: &mut S)
     where
         Tags: MapHash,
@@ -56,39 +135,78 @@ impl<Tags: PtrList> RawPtrUnion<Tags> {
         Tags::map_hash(ptr, tag, state)
     }
 
+    /// Converts this union into the pointer list, taking ownership of the active pointer
+    ///
+    /// # Safety
+    ///
+    /// The same as [`map_any`](Self::map_any). If the active pointer type isn't `Copy`,
+    /// then ownership is transferred to the caller, so the pointer must not be used again
+    /// through any copies of this union
+    pub unsafe fn unpack(self) -> Tags {
+        let (ptr, tag) = self.split();
+        unsafe { Tags::into_inner(ptr, tag) }
+    }
+
+    /// Drops the active pointer
+    ///
+    /// # Safety
+    ///
+    /// The same as [`map_any`](Self::map_any), and this union must own the pointer.
+    /// The pointer must not be used again through any copies of this union
+    pub unsafe fn drop_in_place(self) {
+        let (ptr, tag) = self.split();
+        unsafe { Tags::drop_at(ptr, tag) }
+    }
+
     const fn validate_tag(tag: u8) -> usize {
         #[inline(never)]
         const fn validate_tag_failed() -> ! {
             panic!("invalid tag")
         }
 
-        let tag = tag as usize;
+        let shifted = (tag as usize) << E::TAG_SHIFT;
 
-        if tag & Tags::MASK != tag || (tag as u8) >= Tags::LEN {
+        if shifted & E::TAG_MASK != shifted || tag >= Tags::LEN {
             validate_tag_failed()
         }
 
-        tag
+        shifted
     }
 
+    /// Tags the pointer with `tag`, or returns an error if the pointer doesn't have
+    /// enough free bits to store the tag
+    ///
+    /// # Panics
+    ///
+    /// if `tag >= Tags::LEN`
     pub const fn try_from_raw(ptr: NonNull<()>, tag: u8) -> Result<Self, InvalidAlignment> {
         let addr = addr(ptr).get();
 
         Self::validate_tag(tag);
 
-        if addr & Tags::MASK == 0 {
+        if addr & (E::TAG_MASK | E::FLAG_MASK) == 0 {
             Ok(Self::from_raw(ptr, tag))
         } else {
             Err(InvalidAlignment)
         }
     }
 
+    /// Tags the pointer with `tag`
+    ///
+    /// This doesn't take ownership of the pointer, but to use any of the `unsafe`
+    /// methods later `ptr` should come from `ErasablePtr::into_raw` of the pointer type
+    /// at index `tag` in `Tags`.
+    ///
+    /// # Panics
+    ///
+    /// if `tag >= Tags::LEN` or if any of the bits in `E::TAG_MASK` or `E::FLAG_MASK` are set in the pointer,
+    /// for [`LowBits`] that means that the pointer isn't aligned to at least `Tags::MASK + 1`
     pub const fn from_raw(ptr: NonNull<()>, tag: u8) -> Self {
         let addr = addr(ptr).get();
         let tag = Self::validate_tag(tag);
 
         assert!(
-            addr & Tags::MASK == 0,
+            addr & (E::TAG_MASK | E::FLAG_MASK) == 0,
             "Invalid pointer alignment for this PtrUnion"
         );
 
@@ -103,16 +221,22 @@ impl<Tags: PtrList> RawPtrUnion<Tags> {
         }
     }
 
-    const unsafe fn from_raw_unchecked(ptr: NonNull<()>, tag: u8) -> Self {
+    /// Tags the pointer with `tag` without checking the tag or the alignment
+    ///
+    /// # Safety
+    ///
+    /// `tag` must be less than `Tags::LEN`, and none of the bits in `E::TAG_MASK` or
+    /// `E::FLAG_MASK` may be set in `ptr`
+    pub const unsafe fn from_raw_unchecked(ptr: NonNull<()>, tag: u8) -> Self {
         let addr = addr(ptr).get();
 
         debug_assert!(tag < Tags::LEN);
         debug_assert!(
-            addr & Tags::MASK == 0,
+            addr & (E::TAG_MASK | E::FLAG_MASK) == 0,
             "Invalid pointer alignment for this PtrUnion"
         );
 
-        let tag = tag as usize;
+        let tag = (tag as usize) << E::TAG_SHIFT;
         Self {
             // SAFETY: we checked that we won't overflow right above
             // validate_tag checks that the tag fits in the MASK and
@@ -124,72 +248,103 @@ impl<Tags: PtrList> RawPtrUnion<Tags> {
         }
     }
 
-    pub fn try_to_superset<NewTags, Ns>(self) -> Result<RawPtrUnion<NewTags>, InvalidAlignment>
+    /// Re-tags this union for the superset `NewTags`, or returns an error if the
+    /// pointer isn't aligned enough for the larger tag
+    ///
+    /// All of the conversions keep the flags.
+    pub fn try_to_superset<NewTags, Ns>(self) -> Result<RawPtrUnion<NewTags, E>, InvalidAlignment>
     where
         Tags: SubsetOf<NewTags, Ns>,
         NewTags: PtrList,
+        E: PackedEncoding<NewTags>,
     {
         let (ptr, tag) = self.split();
 
         let tag = unsafe { Tags::map_tag_to_superset(tag) };
 
-        RawPtrUnion::try_from_raw(ptr, tag)
+        RawPtrUnion::try_from_raw(ptr, tag).map(|raw| raw.with_flags(self.flags()))
     }
 
-    pub fn to_superset<NewTags, Ns>(self) -> RawPtrUnion<NewTags>
+    /// Re-tags this union for the superset `NewTags`
+    ///
+    /// # Panics
+    ///
+    /// if the pointer isn't aligned enough for the larger tag
+    pub fn to_superset<NewTags, Ns>(self) -> RawPtrUnion<NewTags, E>
     where
         Tags: SubsetOf<NewTags, Ns>,
         NewTags: PtrList,
+        E: PackedEncoding<NewTags>,
     {
         let (ptr, tag) = self.split();
 
         let tag = unsafe { Tags::map_tag_to_superset(tag) };
 
-        RawPtrUnion::from_raw(ptr, tag)
+        RawPtrUnion::from_raw(ptr, tag).with_flags(self.flags())
     }
 
-    pub fn reorganize<NewTags, Ns>(self) -> RawPtrUnion<NewTags>
+    /// Re-tags this union for `NewTags`, which has the same pointer types in a different order
+    pub fn reorganize<NewTags, Ns>(self) -> RawPtrUnion<NewTags, E>
     where
         Tags: SubsetOf<NewTags, Ns, Remaining = Nil>,
         NewTags: PtrList,
+        E: PackedEncoding<NewTags>,
     {
         assert_eq!(NewTags::LEN, Tags::LEN);
-        assert_eq!(NewTags::MASK, Tags::MASK);
+        assert_eq!(
+            <E as PackedEncoding<NewTags>>::TAG_MASK,
+            <E as PackedEncoding<Tags>>::TAG_MASK
+        );
 
         let (ptr, tag) = self.split();
 
         let tag = unsafe { Tags::map_tag_to_superset(tag) };
 
         // the new tags have exactly the same number of elements as the current tags
-        unsafe { RawPtrUnion::from_raw_unchecked(ptr, tag) }
+        unsafe { RawPtrUnion::from_raw_unchecked(ptr, tag) }.with_flags(self.flags())
     }
 
+    /// Re-tags this union for the subset `NewTags` if the active variant is in `NewTags`,
+    /// otherwise re-tags it for the remaining pointer types
+    ///
+    /// The pointer is never changed, only the tag, so this is always safe.
     #[allow(clippy::manual_map)]
     pub fn try_to_subset<NewTags, Ns>(
         self,
-    ) -> Result<RawPtrUnion<NewTags>, RawPtrUnion<NewTags::Remaining>>
+    ) -> Result<RawPtrUnion<NewTags, E>, RawPtrUnion<NewTags::Remaining, E>>
     where
         NewTags: SubsetOf<Tags, Ns>,
         NewTags: PtrList,
         NewTags::Remaining: PtrList,
+        E: PackedEncoding<NewTags> + PackedEncoding<NewTags::Remaining>,
     {
         let (ptr, tag) = self.split();
+        let flags = self.flags();
 
         match unsafe { NewTags::try_map_tag_to_subset(tag, tag) } {
             // the ptr mask will always be smaller in a subset
-            Ok(tag) => Ok(unsafe { RawPtrUnion::from_raw_unchecked(ptr, tag) }),
-            Err(new_tag) => Err(unsafe { RawPtrUnion::from_raw_unchecked(ptr, new_tag) }),
+            Ok(tag) => Ok(unsafe { RawPtrUnion::from_raw_unchecked(ptr, tag) }.with_flags(flags)),
+            Err(new_tag) => {
+                Err(unsafe { RawPtrUnion::from_raw_unchecked(ptr, new_tag) }.with_flags(flags))
+            }
         }
     }
 }
 
-impl<T: ErasablePtr> RawPtrUnion<TypeList![T]> {
+impl<T: ErasablePtr, E: PackedEncoding<TypeList![T]>> RawPtrUnion<TypeList![T], E> {
+    /// Converts this union back into the only pointer type in the list
+    ///
+    /// # Safety
+    ///
+    /// The pointer must have come from `T::into_raw`, and ownership of that pointer is
+    /// transferred to the caller, so it must not be used again through any copies of this union
     pub unsafe fn into_inner(self) -> T {
-        unsafe { T::from_raw(self.ptr.cast()) }
+        unsafe { T::from_raw(self.split().0) }
     }
 }
 
-impl RawPtrUnion<TypeList![]> {
+impl<E> RawPtrUnion<TypeList![], E> {
+    /// An empty pointer list has no variants, so this can never be called
     pub fn unreachable(self) -> ! {
         unsafe { core::hint::unreachable_unchecked() }
     }
@@ -0,0 +1,154 @@
+use super::*;
+
+use core::mem::MaybeUninit;
+
+/// A union of references stored as a tagged offset from its own address
+///
+/// Because the offset is relative to the union itself, a buffer which contains both the
+/// union and its target can be copied to a different address (written to a file, mapped
+/// into another process) and the union still points at the same place in the buffer.
+/// The flip side is that moving a `RelPtrUnion` on its own breaks it, so it is only useful
+/// in place, and it is neither `Copy` nor `Clone`.
+///
+/// The tag is stored in the low bits of the offset, so the offset must be aligned to at
+/// least `Tags::MASK + 1`. Use [`validate`](Self::validate) before reading a union
+/// from an untrusted buffer, or from a copy of a buffer which may be less aligned than the
+/// original.
+#[repr(transparent)]
+pub struct RelPtrUnion<Tags> {
+    raw: isize,
+    _ty: PhantomData<Tags>,
+}
+
+/// The reason that [`RelPtrUnion::validate`] rejected a union
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InvalidRelPtr {
+    /// The union or its target aren't entirely inside of the buffer
+    OutOfBounds,
+    /// The target isn't aligned for the pointer type of the tag, or to `Tags::MASK + 1`
+    Misaligned,
+    /// The tag is larger than the number of pointer types
+    InvalidTag,
+}
+
+impl<Tags: PtrList> RelPtrUnion<Tags> {
+    fn offset_to(&self, ptr: NonNull<()>) -> isize {
+        (addr(ptr).get() as isize).wrapping_sub(self as *const Self as isize)
+    }
+
+    fn split(&self) -> (isize, u8) {
+        let tag = self.raw as usize & Tags::MASK;
+        (self.raw - tag as isize, tag as u8)
+    }
+
+    /// Initializes `slot` to point at the active reference of `ptr`
+    ///
+    /// # Panics
+    ///
+    /// if the offset from `slot` to the target isn't aligned to at least `Tags::MASK + 1`
+    pub fn init(slot: &mut MaybeUninit<Self>, ptr: CopyPtrUnion<Tags>) -> &mut Self {
+        let this = slot.write(Self {
+            raw: 0,
+            _ty: PhantomData,
+        });
+        this.set(ptr);
+        this
+    }
+
+    /// Points this union at the active reference of `ptr`, or returns an error if the offset
+    /// from this union to the target isn't aligned to at least `Tags::MASK + 1`
+    pub fn try_set(&mut self, ptr: CopyPtrUnion<Tags>) -> Result<(), InvalidAlignment> {
+        let (ptr, tag) = ptr.split();
+        let offset = self.offset_to(ptr);
+
+        if offset as usize & Tags::MASK == 0 {
+            self.raw = offset | tag as isize;
+            Ok(())
+        } else {
+            Err(InvalidAlignment)
+        }
+    }
+
+    /// Points this union at the active reference of `ptr`
+    ///
+    /// # Panics
+    ///
+    /// if the offset from this union to the target isn't aligned to at least `Tags::MASK + 1`
+    pub fn set(&mut self, ptr: CopyPtrUnion<Tags>) {
+        if self.try_set(ptr).is_err() {
+            panic!("Invalid pointer alignment for this PtrUnion")
+        }
+    }
+
+    /// The tag stored in this union, which is only guaranteed to be less than `Tags::LEN`
+    /// if the union was set or validated
+    pub fn tag(&self) -> usize {
+        self.split().1 as usize
+    }
+
+    /// Checks that this union and its target are inside of `buffer`, that the tag is valid,
+    /// and that the target is aligned for the pointer type of the tag and to `Tags::MASK + 1`
+    ///
+    /// This doesn't check that the bytes of the target are a valid value of the target type.
+    pub fn validate(&self, buffer: &[u8]) -> Result<(), InvalidRelPtr>
+    where
+        Tags: AlignedList,
+    {
+        let start = buffer.as_ptr() as usize;
+        let end = start + buffer.len();
+        let this = self as *const Self as usize;
+
+        if this < start || this > end || end - this < core::mem::size_of::<Self>() {
+            return Err(InvalidRelPtr::OutOfBounds);
+        }
+
+        let (offset, tag) = self.split();
+
+        if tag >= Tags::LEN {
+            return Err(InvalidRelPtr::InvalidTag);
+        }
+
+        let target = match this.checked_add_signed(offset) {
+            Some(target) if target >= start && target <= end => target,
+            _ => return Err(InvalidRelPtr::OutOfBounds),
+        };
+
+        if end - target < Tags::SIZES[tag as usize] {
+            return Err(InvalidRelPtr::OutOfBounds);
+        }
+
+        if target & ((1 << Tags::ALIGN_BITS[tag as usize]) - 1) != 0 || target & Tags::MASK != 0 {
+            return Err(InvalidRelPtr::Misaligned);
+        }
+
+        Ok(())
+    }
+
+    /// Reads the union of references that this points to, inside of `buffer`
+    ///
+    /// The target is derived from `buffer`, which must be the whole buffer that contains
+    /// this union and its target, so that the references may read the target.
+    ///
+    /// # Safety
+    ///
+    /// This union must either have been set at its current address, or have been validated
+    /// against `buffer` since it was last moved or modified. Copying a buffer without
+    /// validating it is only fine if the copy is aligned to the largest alignment of the
+    /// targets and to `Tags::MASK + 1`, like the original.
+    ///
+    /// The target must be inside of `buffer`, and must be a valid value of the target type
+    /// of the pointer type at the tag. The references in the returned union have whatever
+    /// lifetimes `Tags` names, which aren't tied to `buffer`, so the caller must keep the
+    /// buffer alive and unmodified for as long as they are used.
+    pub unsafe fn get(&self, buffer: &[u8]) -> CopyPtrUnion<Tags> {
+        let (offset, tag) = self.split();
+        let target = (self as *const Self as usize).wrapping_add_signed(offset);
+        debug_assert!(target.wrapping_sub(buffer.as_ptr() as usize) <= buffer.len());
+        // a pointer derived from `self` could only read the union itself, so the target
+        // takes the provenance of the whole buffer instead
+        let ptr = buffer.as_ptr().with_addr(target);
+        let ptr = unsafe { NonNull::new_unchecked(ptr.cast_mut().cast()) };
+
+        unsafe { CopyPtrUnion::<Tags>::from_raw(RawPtrUnion::from_raw_unchecked(ptr, tag)) }
+    }
+}
@@ -0,0 +1,68 @@
+use super::*;
+
+#[doc(hidden)]
+pub const __SENTINEL_ALIGN_BITS: u8 = 15;
+
+#[doc(hidden)]
+pub const fn __sentinel_ptr() -> NonNull<()> {
+    // a well aligned address which is never dereferenced, the same one that `Inline` uses
+    // for zero and that replaces dangling pointers to zero-sized targets
+    let addr = 1 << __SENTINEL_ALIGN_BITS;
+    unsafe { NonNull::new_unchecked(core::ptr::without_provenance_mut(addr)) }
+}
+
+/// Defines a marker type which can be used as a variant without a payload in a pointer list
+///
+/// The marker doesn't point to anything, it is stored as a fixed address with the low
+/// 15 bits clear, and it reports those bits through [`DerefRaw::ALIGN_BITS`](crate::DerefRaw),
+/// so it fits with any tag encoding. Dropping it is a no-op.
+/// [`map_any`](crate::PtrUnion::map_any), `Eq` and `Hash` see a reference to the marker.
+///
+/// ```
+/// ptr_tags::sentinel! {
+///     /// The slot was never filled
+///     pub struct Vacant;
+/// }
+///
+/// let x = ptr_tags::PtrUnion::<ptr_tags::TypeList![Vacant, Box<u32>]>::new(Vacant);
+/// assert!(x.is::<Vacant, _>());
+/// ```
+#[macro_export]
+macro_rules! sentinel {
+    ($($(#[$meta:meta])* $vis:vis struct $name:ident;)*) => {$(
+        $(#[$meta])*
+        #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+        $vis struct $name;
+
+        unsafe impl $crate::ErasablePtr for $name {
+            fn into_raw(_this: Self) -> ::core::ptr::NonNull<()> {
+                $crate::__sentinel_ptr()
+            }
+
+            unsafe fn from_raw(_ptr: ::core::ptr::NonNull<()>) -> Self {
+                $name
+            }
+        }
+
+        unsafe impl $crate::DerefRaw for $name {
+            type Target = Self;
+
+            const ALIGN_BITS: u8 = $crate::__SENTINEL_ALIGN_BITS;
+
+            unsafe fn with_target<R>(
+                _ptr: ::core::ptr::NonNull<()>,
+                f: impl FnOnce(&Self::Target) -> R,
+            ) -> R {
+                f(&$name)
+            }
+        }
+    )*};
+}
+
+sentinel! {
+    /// A variant without a payload, for example to make an optional pointer
+    pub struct Empty;
+
+    /// A variant without a payload which marks a removed entry
+    pub struct Tombstone;
+}
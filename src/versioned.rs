@@ -0,0 +1,139 @@
+use super::*;
+
+use core::sync::atomic::{AtomicPtr, Ordering};
+use encoding::spare_bits;
+
+/// An atomic [`CopyPtrUnion`] with a version counter in the spare low bits of the pointer,
+/// to detect the ABA problem in lock-free data structures
+///
+/// The tag is stored in the low bits like [`LowBits`], and every other bit which is zero
+/// because of the alignment of the targets holds the version. The version is incremented
+/// (wrapping around after [`VERSION_BITS`](Self::VERSION_BITS) bits) on every successful
+/// [`compare_exchange`](Self::compare_exchange), and a compare exchange only succeeds if
+/// both the pointer and the version match. A list without any spare bits fails to compile
+/// when the `VersionedPtr` is constructed.
+pub struct VersionedPtr<Tags: AlignedList> {
+    ptr: AtomicPtr<u8>,
+    _ty: PhantomData<*mut Tags>,
+}
+
+unsafe impl<Tags: AlignedList + Send> Send for VersionedPtr<Tags> {}
+// loading from a shared reference hands out copies of the pointers to other threads
+unsafe impl<Tags: AlignedList + Send + Sync> Sync for VersionedPtr<Tags> {}
+
+impl<Tags: AlignedList> VersionedPtr<Tags> {
+    /// The width of the version counter
+    pub const VERSION_BITS: u32 = {
+        let bits = match spare_bits::<Tags>().checked_sub(Tags::MASK.count_ones()) {
+            Some(bits) => bits,
+            None => 0,
+        };
+        assert!(
+            bits > 0,
+            "the pointers in this list don't have any spare bits for the version"
+        );
+        bits
+    };
+
+    const VERSION_SHIFT: u32 = Tags::MASK.count_ones();
+
+    const VERSION_MASK: usize = if Self::VERSION_BITS >= usize::BITS {
+        usize::MAX
+    } else {
+        ((1 << Self::VERSION_BITS) - 1) << Self::VERSION_SHIFT
+    };
+
+    fn into_bits(ptr: CopyPtrUnion<Tags>, version: usize) -> *mut u8 {
+        let (ptr, tag) = ptr.split();
+
+        assert!(
+            addr(ptr).get() & Self::VERSION_MASK == 0,
+            "Invalid pointer alignment for this PtrUnion"
+        );
+
+        let raw = unsafe { RawPtrUnion::<Tags>::from_raw_unchecked(ptr, tag) };
+        // we must use wrapping_add here so that we preserve the provenance of the pointer
+        raw.into_tagged()
+            .as_ptr()
+            .wrapping_add((version << Self::VERSION_SHIFT) & Self::VERSION_MASK)
+    }
+
+    fn from_bits(bits: *mut u8) -> (CopyPtrUnion<Tags>, usize) {
+        let version = bits.addr() & Self::VERSION_MASK;
+        let ptr = bits.wrapping_sub(version);
+        // only non-null pointers from `into_bits` are ever stored
+        let ptr = unsafe { NonNull::new_unchecked(ptr) };
+        let ptr = unsafe { CopyPtrUnion::<Tags>::from_raw(RawPtrUnion::from_tagged(ptr)) };
+
+        (ptr, version >> Self::VERSION_SHIFT)
+    }
+
+    /// Creates a new `VersionedPtr` with version zero
+    ///
+    /// # Panics
+    ///
+    /// if the pointer isn't aligned to at least `1 << (Tags::MASK.count_ones() + VERSION_BITS)`
+    pub fn new(ptr: CopyPtrUnion<Tags>) -> Self {
+        Self {
+            ptr: AtomicPtr::new(Self::into_bits(ptr, 0)),
+            _ty: PhantomData,
+        }
+    }
+
+    pub fn into_inner(self) -> (CopyPtrUnion<Tags>, usize) {
+        Self::from_bits(self.ptr.into_inner())
+    }
+
+    /// Loads the current pointer and version
+    pub fn load(&self, order: Ordering) -> (CopyPtrUnion<Tags>, usize) {
+        Self::from_bits(self.ptr.load(order))
+    }
+
+    /// Stores `new` with the next version if the current pointer, tag and version are the
+    /// same as `current`, and returns the previous pointer and version either way
+    ///
+    /// # Panics
+    ///
+    /// if `new` isn't aligned like in [`new`](Self::new)
+    pub fn compare_exchange(
+        &self,
+        current: (CopyPtrUnion<Tags>, usize),
+        new: CopyPtrUnion<Tags>,
+        success: Ordering,
+        failure: Ordering,
+    ) -> Result<(CopyPtrUnion<Tags>, usize), (CopyPtrUnion<Tags>, usize)> {
+        let (current, version) = current;
+
+        self.ptr
+            .compare_exchange(
+                Self::into_bits(current, version),
+                Self::into_bits(new, version.wrapping_add(1)),
+                success,
+                failure,
+            )
+            .map(Self::from_bits)
+            .map_err(Self::from_bits)
+    }
+
+    /// Like [`compare_exchange`](Self::compare_exchange), but may fail spuriously,
+    /// see [`AtomicPtr::compare_exchange_weak`]
+    pub fn compare_exchange_weak(
+        &self,
+        current: (CopyPtrUnion<Tags>, usize),
+        new: CopyPtrUnion<Tags>,
+        success: Ordering,
+        failure: Ordering,
+    ) -> Result<(CopyPtrUnion<Tags>, usize), (CopyPtrUnion<Tags>, usize)> {
+        let (current, version) = current;
+
+        self.ptr
+            .compare_exchange_weak(
+                Self::into_bits(current, version),
+                Self::into_bits(new, version.wrapping_add(1)),
+                success,
+                failure,
+            )
+            .map(Self::from_bits)
+            .map_err(Self::from_bits)
+    }
+}
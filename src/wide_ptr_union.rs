@@ -0,0 +1,99 @@
+use super::*;
+
+/// Stores the tag next to the pointer instead of inside it
+///
+/// This doubles the size of the union, but places no requirements on the pointers,
+/// so it can be used when the pointers in a list don't have enough alignment for [`LowBits`]
+pub enum Wide {}
+
+impl sealed::SealedEncoding for Wide {}
+
+/// A pointer and the index of its pointer type in `Tags`, stored side by side
+///
+/// This is the [`TagEncoding::Raw`] of [`Wide`], and like [`RawPtrUnion`] it doesn't own the pointer
+pub struct RawWidePtrUnion<Tags> {
+    ptr: NonNull<()>,
+    tag: u8,
+    _ty: PhantomData<Tags>,
+}
+
+unsafe impl<Tags: Send> Send for RawWidePtrUnion<Tags> {}
+unsafe impl<Tags: Sync> Sync for RawWidePtrUnion<Tags> {}
+
+impl<Tags> Copy for RawWidePtrUnion<Tags> {}
+impl<Tags> Clone for RawWidePtrUnion<Tags> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<Tags: PtrList> RawWidePtrUnion<Tags> {
+    /// Splits this union into the pointer and the tag, the tag is always less than `Tags::LEN`
+    pub const fn split(&self) -> (NonNull<()>, u8) {
+        if self.tag >= Tags::LEN {
+            unsafe { core::hint::unreachable_unchecked() }
+        }
+
+        (self.ptr, self.tag)
+    }
+
+    /// Tags the pointer with `tag`
+    ///
+    /// # Panics
+    ///
+    /// if `tag >= Tags::LEN`
+    pub const fn from_raw(ptr: NonNull<()>, tag: u8) -> Self {
+        assert!(tag < Tags::LEN, "invalid tag");
+
+        Self {
+            ptr,
+            tag,
+            _ty: PhantomData,
+        }
+    }
+
+    /// Tags the pointer with `tag` without checking the tag
+    ///
+    /// # Safety
+    ///
+    /// `tag` must be less than `Tags::LEN`
+    pub const unsafe fn from_raw_unchecked(ptr: NonNull<()>, tag: u8) -> Self {
+        debug_assert!(tag < Tags::LEN);
+
+        Self {
+            ptr,
+            tag,
+            _ty: PhantomData,
+        }
+    }
+}
+
+unsafe impl<Tags: PtrList> TagEncoding<Tags> for Wide {
+    type Raw = RawWidePtrUnion<Tags>;
+
+    fn split(raw: &Self::Raw) -> (NonNull<()>, u8) {
+        raw.split()
+    }
+
+    fn try_from_raw(ptr: NonNull<()>, tag: u8) -> Result<Self::Raw, InvalidAlignment> {
+        Ok(RawWidePtrUnion::from_raw(ptr, tag))
+    }
+
+    fn from_raw(ptr: NonNull<()>, tag: u8) -> Self::Raw {
+        RawWidePtrUnion::from_raw(ptr, tag)
+    }
+
+    unsafe fn from_raw_unchecked(ptr: NonNull<()>, tag: u8) -> Self::Raw {
+        unsafe { RawWidePtrUnion::from_raw_unchecked(ptr, tag) }
+    }
+}
+
+/// A [`PtrUnion`] which stores the tag next to the pointer, so it takes two words
+/// but works with pointers of any alignment
+///
+/// Choosing between this and the compact [`PtrUnion`] from the alignment of the list
+/// would need `generic_const_exprs`, so the encoding has to be picked explicitly.
+/// [`AlignedList::FITS_LOW_BITS`] tells at compile time which one a list needs, for
+/// example `const { assert!(<MyList as AlignedList>::FITS_LOW_BITS) }` next to a
+/// `PtrUnion<MyList>` catches a list which outgrew its alignment.
+pub type WidePtrUnion<Tags, O = Owned> = PtrUnion<Tags, O, Wide>;
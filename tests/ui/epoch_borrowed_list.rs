@@ -0,0 +1,9 @@
+use ptr_tags::{epoch::AtomicPtrUnion, PtrUnion, TypeList};
+
+struct Foo<'a>(&'a u32);
+
+fn main() {
+    let a = 0u32;
+    let x = AtomicPtrUnion::new(PtrUnion::<TypeList![Box<Foo<'_>>]>::new(Box::new(Foo(&a))));
+    x.store(PtrUnion::new(Box::new(Foo(&a))));
+}
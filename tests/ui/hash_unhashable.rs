@@ -0,0 +1,11 @@
+use ptr_tags::{CopyPtrUnion, TypeList};
+
+struct NotHash;
+
+fn hash<T: core::hash::Hash>(_: &T) {}
+
+fn main() {
+    let a = NotHash;
+    let x = CopyPtrUnion::<TypeList![&u32, &NotHash]>::new(&a);
+    hash(&x);
+}
@@ -0,0 +1,19 @@
+use ptr_tags::{CopyPtrUnion, Mapper, MapperOutput, TypeList};
+
+struct Double;
+
+impl MapperOutput for Double {
+    type Output = u32;
+}
+
+impl Mapper<u32> for Double {
+    fn call(self, value: &u32) -> u32 {
+        value * 2
+    }
+}
+
+fn main() {
+    let a = 0u32;
+    let x = CopyPtrUnion::<TypeList![&u32, &i32]>::new(&a);
+    let _ = x.map_any(Double);
+}
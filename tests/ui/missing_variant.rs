@@ -0,0 +1,5 @@
+use ptr_tags::{PtrUnion, TypeList};
+
+fn main() {
+    let _ = PtrUnion::<TypeList![Box<u32>, Box<i32>]>::new(Box::new(5u64));
+}
@@ -0,0 +1,7 @@
+use ptr_tags::{CopyPtrUnion, TypeList};
+
+fn main() {
+    let a = 0u32;
+    let x = CopyPtrUnion::<TypeList![&u32, &i32]>::new(&a);
+    let _: CopyPtrUnion<TypeList![&u32]> = x.to_superset();
+}
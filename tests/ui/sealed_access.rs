@@ -0,0 +1,13 @@
+use ptr_tags::{Access, Cons, Nil, Z};
+
+struct Mine;
+
+unsafe impl Access<Mine, Z> for Cons<Box<u8>, Nil> {
+    type Remaining = Nil;
+
+    unsafe fn new(_ptr: core::ptr::NonNull<()>) -> Self {
+        unreachable!()
+    }
+}
+
+fn main() {}
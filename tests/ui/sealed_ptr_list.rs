@@ -0,0 +1,17 @@
+use core::ptr::NonNull;
+
+use ptr_tags::PtrList;
+
+enum MyList {}
+
+unsafe impl PtrList for MyList {
+    const LEN: u8 = 200;
+
+    unsafe fn into_inner(_ptr: NonNull<()>, _tag: u8) -> Self {
+        unreachable!()
+    }
+
+    unsafe fn drop_at(_ptr: NonNull<()>, _tag: u8) {}
+}
+
+fn main() {}